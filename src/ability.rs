@@ -0,0 +1,160 @@
+use amethyst::{
+    core::{transform::Transform, Time},
+    ecs::prelude::{Entities, Entity, Join, Read, ReadStorage, System, WriteStorage},
+    renderer::SpriteRender,
+    core::math::Vector3,
+};
+use serde::{Deserialize, Serialize};
+use crate::unit::Unit;
+use crate::effect::{EffectTypes, StatusEffects};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Ability {
+    // Heals allies in `radius` by `amount` every `interval` seconds
+    HealAura { radius: f32, amount: f32, interval: f32 },
+    // Applies a status effect to allies in `radius` every `interval` seconds
+    Aura { radius: f32, effect: String, interval: f32 },
+    // Absorbs up to `amount` damage for `duration` seconds, renewed every `interval` seconds
+    Shield { amount: f32, duration: f32, interval: f32 },
+    // Spawns `count` units of type `unit` where the unit died
+    SpawnOnDeath { unit: String, count: u32 },
+}
+
+fn default_multiplier() -> f32 {
+    1.0
+}
+
+// Boss phase starts when unit health drops to `health` part of `max_health`
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct BossPhase {
+    pub health: f32,
+    // Replaces unit abilities when not empty
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    #[serde(default = "default_multiplier")]
+    pub speed_multiplier: f32,
+    #[serde(default)]
+    pub sprite_id: Option<u32>,
+}
+
+enum AuraAction {
+    Heal(f32),
+    Effect(String),
+}
+
+struct PendingAura {
+    team: u32,
+    pos: Vector3<f32>,
+    radius: f32,
+    action: AuraAction,
+}
+
+#[derive(Default)]
+pub struct AbilitySystem;
+
+impl<'s> System<'s> for AbilitySystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Unit>,
+        WriteStorage<'s, StatusEffects>,
+        WriteStorage<'s, SpriteRender>,
+        ReadStorage<'s, Transform>,
+        Read<'s, EffectTypes>,
+        Read<'s, Time>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut units, mut status_effects, mut sprites, transforms, effect_types, time): Self::SystemData,
+    ) {
+        let delta = time.delta_seconds();
+        let mut auras = Vec::<PendingAura>::new();
+
+        for (entity, unit, transform) in (&entities, &mut units, &transforms).join() {
+            if unit.health <= 0.0 {
+                continue;
+            }
+            update_phase(entity, unit, &mut sprites);
+
+            unit.shield_time -= delta;
+            if unit.shield_time <= 0.0 {
+                unit.shield = 0.0;
+            }
+
+            let abilities = unit.unit_type.abilities.clone();
+            unit.ability_timers.resize(abilities.len(), 0.0);
+            for (i, ability) in abilities.iter().enumerate() {
+                unit.ability_timers[i] -= delta;
+                if unit.ability_timers[i] > 0.0 {
+                    continue;
+                }
+
+                let pos = *transform.translation();
+                match ability {
+                    Ability::HealAura { radius, amount, interval } => {
+                        auras.push(PendingAura {
+                            team: unit.team, pos: pos, radius: *radius, action: AuraAction::Heal(*amount),
+                        });
+                        unit.ability_timers[i] = *interval;
+                    },
+                    Ability::Aura { radius, effect, interval } => {
+                        auras.push(PendingAura {
+                            team: unit.team, pos: pos, radius: *radius, action: AuraAction::Effect(effect.clone()),
+                        });
+                        unit.ability_timers[i] = *interval;
+                    },
+                    Ability::Shield { amount, duration, interval } => {
+                        unit.shield = *amount;
+                        unit.shield_time = *duration;
+                        unit.ability_timers[i] = *interval;
+                    },
+                    // Handled by `UnitDeathSystem`
+                    Ability::SpawnOnDeath { .. } => unit.ability_timers[i] = std::f32::MAX,
+                }
+            }
+        }
+
+        for aura in auras {
+            for (entity, unit, transform) in (&entities, &mut units, &transforms).join() {
+                let offset = transform.translation() - aura.pos;
+                if unit.team != aura.team || unit.health <= 0.0
+                    || Vector3::new(offset.x, offset.y, 0.0).norm() > aura.radius
+                {
+                    continue;
+                }
+                match &aura.action {
+                    AuraAction::Heal(amount) => {
+                        unit.health = (unit.health + amount).min(unit.unit_type.max_health);
+                    },
+                    AuraAction::Effect(name) => {
+                        if let (Some(effects), Some(effect_type)) = (status_effects.get_mut(entity), effect_types.get(name)) {
+                            effects.apply(effect_type);
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
+
+// Switches boss units to the next phases once their health is low enough
+fn update_phase(entity: Entity, unit: &mut Unit, sprites: &mut WriteStorage<'_, SpriteRender>) {
+    while let Some(phase) = unit.unit_type.phases.get(unit.phase).cloned() {
+        if unit.health > phase.health * unit.unit_type.max_health {
+            break;
+        }
+        if !phase.abilities.is_empty() {
+            unit.unit_type.abilities = phase.abilities;
+            unit.ability_timers.clear();
+        }
+        unit.unit_type.speed *= phase.speed_multiplier;
+        if let Some(sprite_id) = phase.sprite_id {
+            unit.unit_type.sprite_id = sprite_id;
+            if let Some(sprite) = sprites.get_mut(entity) {
+                sprite.sprite_number = sprite_id as usize;
+            }
+        }
+        unit.phase += 1;
+    }
+}
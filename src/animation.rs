@@ -0,0 +1,303 @@
+use amethyst::{
+    core::{transform::Transform, Time},
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Join, LazyUpdate, Read, ReadStorage, System, WriteStorage,
+    },
+    renderer::SpriteRender,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::unit::{SpriteData, Unit};
+use crate::effect::StatusEffects;
+
+// Range of sprites played one after another
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct AnimationClip {
+    pub first: u32,
+    pub last: u32,
+    // Seconds per frame
+    pub frame_time: f32,
+    #[serde(default)]
+    pub looping: bool,
+    // Sprite sheet of the clip, the entity sprite sheet is used when not set
+    #[serde(default)]
+    pub sprite_name: Option<String>,
+}
+
+impl AnimationClip {
+    // Seconds to play every frame once
+    pub fn duration(&self) -> f32 {
+        (self.last.saturating_sub(self.first) + 1) as f32 * self.frame_time
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnimationState {
+    Idle,
+    Walk,
+    Attack,
+    Death,
+}
+
+impl AnimationState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimationState::Idle => "idle",
+            AnimationState::Walk => "walk",
+            AnimationState::Attack => "attack",
+            AnimationState::Death => "death",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn from_heading(x: f32, y: f32) -> Direction {
+        if x.abs() >= y.abs() {
+            if x >= 0.0 { Direction::Right } else { Direction::Left }
+        } else {
+            if y >= 0.0 { Direction::Up } else { Direction::Down }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+}
+
+// Clips are looked up as "walk_left" first and as "walk" after that
+pub struct Animation {
+    pub clips: HashMap<String, AnimationClip>,
+    pub sprite_name: String,
+    pub state: AnimationState,
+    pub direction: Direction,
+    // Delete the entity once a not looping clip is finished
+    pub despawn: bool,
+    current: Option<String>,
+    frame: u32,
+    timer: f32,
+    finished: bool,
+}
+
+impl Component for Animation {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Animation {
+    pub fn new(clips: HashMap<String, AnimationClip>, sprite_name: String) -> Animation {
+        Animation {
+            clips: clips,
+            sprite_name: sprite_name,
+            state: AnimationState::Idle,
+            direction: Direction::Left,
+            despawn: false,
+            current: None,
+            frame: 0,
+            timer: 0.0,
+            finished: false,
+        }
+    }
+
+    // Animation which plays a single clip and removes its entity
+    pub fn one_shot(clip: AnimationClip, sprite_name: String) -> Animation {
+        let mut clips = HashMap::new();
+        clips.insert(AnimationState::Idle.name().to_string(), clip);
+
+        let mut animation = Animation::new(clips, sprite_name);
+        animation.despawn = true;
+        animation
+    }
+
+    fn clip_name(&self) -> Option<String> {
+        let directional = format!("{}_{}", self.state.name(), self.direction.name());
+        if self.clips.contains_key(&directional) {
+            return Some(directional);
+        }
+        if self.clips.contains_key(self.state.name()) {
+            return Some(self.state.name().to_string());
+        }
+        None
+    }
+}
+
+pub fn spawn_one_shot(
+    lazy_update: &LazyUpdate,
+    entities: &Entities<'_>,
+    sprite_data: &SpriteData,
+    clip: &AnimationClip,
+    default_sprite_name: &str,
+    transform: Transform,
+) {
+    let sprite_name = clip.sprite_name.clone().unwrap_or(default_sprite_name.to_string());
+    let sprite_sheet = match sprite_data.sprite_handles.get(&sprite_name) {
+        Some(e) => e.clone(),
+        None => return,
+    };
+
+    lazy_update
+        .create_entity(entities)
+        .with(SpriteRender {
+            sprite_sheet: sprite_sheet,
+            sprite_number: clip.first as usize,
+        })
+        .with(Animation::one_shot(clip.clone(), sprite_name))
+        .with(transform)
+        .build();
+}
+
+#[derive(Default)]
+pub struct AnimationSystem;
+
+impl<'s> System<'s> for AnimationSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Animation>,
+        WriteStorage<'s, SpriteRender>,
+        Read<'s, SpriteData>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, mut animations, mut sprites, sprite_data, time): Self::SystemData) {
+        for (entity, animation, sprite) in (&entities, &mut animations, &mut sprites).join() {
+            let name = match animation.clip_name() {
+                Some(e) => e,
+                None => continue,
+            };
+            let clip = animation.clips[&name].clone();
+            let length = clip.last.saturating_sub(clip.first);
+
+            if animation.current.as_ref() != Some(&name) {
+                let sprite_name = clip.sprite_name.as_ref().unwrap_or(&animation.sprite_name);
+                if let Some(sprite_sheet) = sprite_data.sprite_handles.get(sprite_name) {
+                    sprite.sprite_sheet = sprite_sheet.clone();
+                }
+                animation.current = Some(name);
+                animation.frame = 0;
+                animation.timer = 0.0;
+                animation.finished = false;
+            } else if clip.frame_time > 0.0 && !animation.finished {
+                animation.timer += time.delta_seconds();
+                while animation.timer >= clip.frame_time {
+                    animation.timer -= clip.frame_time;
+                    if animation.frame < length {
+                        animation.frame += 1;
+                    } else if clip.looping {
+                        animation.frame = 0;
+                    } else {
+                        animation.finished = true;
+                        break;
+                    }
+                }
+            }
+            sprite.sprite_number = (clip.first + animation.frame) as usize;
+
+            if animation.finished && animation.despawn {
+                entities.delete(entity).unwrap();
+            }
+        }
+    }
+}
+
+// Picks unit clips from the unit movement and attack
+#[derive(Default)]
+pub struct UnitAnimationSystem;
+
+impl<'s> System<'s> for UnitAnimationSystem {
+    type SystemData = (
+        ReadStorage<'s, Unit>,
+        ReadStorage<'s, StatusEffects>,
+        WriteStorage<'s, Animation>,
+    );
+
+    fn run(&mut self, (units, status_effects, mut animations): Self::SystemData) {
+        for (unit, effects, animation) in (&units, status_effects.maybe(), &mut animations).join() {
+            let moving = effects.map_or(true, |x| x.speed_multiplier() > 0.0)
+                && unit.waypoint < unit.path.len();
+
+            animation.state = if unit.attacking {
+                AnimationState::Attack
+            } else if moving {
+                AnimationState::Walk
+            } else {
+                AnimationState::Idle
+            };
+            animation.direction = Direction::from_heading(unit.heading.0, unit.heading.1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unit::UnitTyes;
+
+    fn clip(first: u32, last: u32) -> AnimationClip {
+        AnimationClip {
+            first: first,
+            last: last,
+            frame_time: 0.1,
+            ..Default::default()
+        }
+    }
+
+    fn animation(names: &[&str]) -> Animation {
+        let clips = names.iter().enumerate().map(|(i, x)| (x.to_string(), clip(i as u32, i as u32))).collect();
+        Animation::new(clips, "sprites".to_string())
+    }
+
+    #[test]
+    fn heading_picks_the_dominant_axis() {
+        assert_eq!(Direction::from_heading(-1.0, 0.5), Direction::Left);
+        assert_eq!(Direction::from_heading(1.0, -0.5), Direction::Right);
+        assert_eq!(Direction::from_heading(0.2, 1.0), Direction::Up);
+        assert_eq!(Direction::from_heading(0.2, -1.0), Direction::Down);
+    }
+
+    #[test]
+    fn directional_clip_wins_over_plain_one() {
+        let mut animation = animation(&["walk", "walk_left", "walk_right"]);
+        animation.state = AnimationState::Walk;
+        animation.direction = Direction::Left;
+        assert_eq!(animation.clip_name(), Some("walk_left".to_string()));
+        animation.direction = Direction::Right;
+        assert_eq!(animation.clip_name(), Some("walk_right".to_string()));
+        animation.direction = Direction::Up;
+        assert_eq!(animation.clip_name(), Some("walk".to_string()));
+    }
+
+    #[test]
+    fn missing_state_has_no_clip() {
+        let mut animation = animation(&["death"]);
+        animation.state = AnimationState::Attack;
+        assert_eq!(animation.clip_name(), None);
+        animation.state = AnimationState::Death;
+        assert_eq!(animation.clip_name(), Some("death".to_string()));
+    }
+
+    #[test]
+    fn shipped_units_have_directional_clips() {
+        let unit_types: UnitTyes = serde_json::from_str(include_str!("../assets/units/info.json")).unwrap();
+        let kobold = unit_types.types.iter().find(|x| x.name == "kobold").unwrap();
+        let mut animation = Animation::new(kobold.animations.clone(), kobold.sprite_name.clone());
+
+        animation.state = AnimationState::Walk;
+        animation.direction = Direction::from_heading(-1.0, 0.0);
+        assert_eq!(animation.clip_name(), Some("walk_left".to_string()));
+        animation.direction = Direction::from_heading(1.0, 0.0);
+        assert_eq!(animation.clip_name(), Some("walk_right".to_string()));
+        animation.state = AnimationState::Attack;
+        assert_eq!(animation.clip_name(), Some("attack_right".to_string()));
+    }
+}
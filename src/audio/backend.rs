@@ -0,0 +1,152 @@
+use amethyst::audio::{
+    output::{default_output, Output},
+    AudioSink, Source,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+// Channels used by the music player, one fades in while the other fades out
+pub const MUSIC_CHANNELS: usize = 2;
+
+// Everything the game asks from the sound device. File paths are relative to the working directory
+pub trait AudioBackend: Send + Sync {
+    // Plays a file once, sounds are mixed together
+    fn play_sound(&mut self, file: &str, volume: f32);
+    // Replaces whatever the channel played with the file
+    fn play_music(&mut self, channel: usize, file: &str, volume: f32);
+    fn set_music_volume(&mut self, channel: usize, volume: f32);
+    fn stop_music(&mut self, channel: usize);
+    // True once the channel has nothing left to play
+    fn music_finished(&self, channel: usize) -> bool;
+}
+
+// Used when there is no sound device, music then advances only by track lengths
+#[derive(Default)]
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn play_sound(&mut self, _file: &str, _volume: f32) {}
+    fn play_music(&mut self, _channel: usize, _file: &str, _volume: f32) {}
+    fn set_music_volume(&mut self, _channel: usize, _volume: f32) {}
+    fn stop_music(&mut self, _channel: usize) {}
+
+    fn music_finished(&self, _channel: usize) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AudioCall {
+    PlaySound { file: String, volume: f32 },
+    PlayMusic { channel: usize, file: String, volume: f32 },
+    SetMusicVolume { channel: usize, volume: f32 },
+    StopMusic { channel: usize },
+}
+
+// Plays nothing but keeps and prints every call, to check what the game would play
+#[derive(Default)]
+pub struct RecordingBackend {
+    calls: Arc<Mutex<Vec<AudioCall>>>,
+}
+
+impl RecordingBackend {
+    // Shared list of the calls, still readable once the backend is moved into the world
+    #[cfg(test)]
+    pub fn calls(&self) -> Arc<Mutex<Vec<AudioCall>>> {
+        self.calls.clone()
+    }
+
+    fn record(&self, call: AudioCall) {
+        println!("Audio: {:?}", call);
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+impl AudioBackend for RecordingBackend {
+    fn play_sound(&mut self, file: &str, volume: f32) {
+        self.record(AudioCall::PlaySound { file: file.to_string(), volume: volume });
+    }
+
+    fn play_music(&mut self, channel: usize, file: &str, volume: f32) {
+        self.record(AudioCall::PlayMusic { channel: channel, file: file.to_string(), volume: volume });
+    }
+
+    fn set_music_volume(&mut self, channel: usize, volume: f32) {
+        self.record(AudioCall::SetMusicVolume { channel: channel, volume: volume });
+    }
+
+    fn stop_music(&mut self, channel: usize) {
+        self.record(AudioCall::StopMusic { channel: channel });
+    }
+
+    fn music_finished(&self, _channel: usize) -> bool {
+        false
+    }
+}
+
+// Plays through the default output device of amethyst audio
+pub struct OutputBackend {
+    output: Output,
+    sinks: Vec<AudioSink>,
+    sources: HashMap<String, Option<Source>>,
+}
+
+// Files are read on first use, None is kept for files that failed to read
+fn load_source<'a>(sources: &'a mut HashMap<String, Option<Source>>, file: &str) -> Option<&'a Source> {
+    sources
+        .entry(file.to_string())
+        .or_insert_with(|| match fs::read(file) {
+            Ok(e) => Some(Source { bytes: e }),
+            Err(e) => {
+                println!("Can't read sound {}: {}", file, e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+impl OutputBackend {
+    // None when the machine has no output device
+    pub fn new() -> Option<OutputBackend> {
+        let output = default_output()?;
+        Some(OutputBackend {
+            sinks: (0..MUSIC_CHANNELS).map(|_| AudioSink::new(&output)).collect(),
+            output: output,
+            sources: HashMap::new(),
+        })
+    }
+}
+
+impl AudioBackend for OutputBackend {
+    fn play_sound(&mut self, file: &str, volume: f32) {
+        if let Some(source) = load_source(&mut self.sources, file) {
+            self.output.play_once(source, volume);
+        }
+    }
+
+    fn play_music(&mut self, channel: usize, file: &str, volume: f32) {
+        self.stop_music(channel);
+        if let Some(source) = load_source(&mut self.sources, file) {
+            let sink = &self.sinks[channel];
+            sink.set_volume(volume);
+            if let Err(e) = sink.append(source) {
+                println!("Can't play music {}: {:?}", file, e);
+            }
+        }
+    }
+
+    fn set_music_volume(&mut self, channel: usize, volume: f32) {
+        self.sinks[channel].set_volume(volume);
+    }
+
+    fn stop_music(&mut self, channel: usize) {
+        // Stopped sinks don't play again
+        self.sinks[channel].stop();
+        self.sinks[channel] = AudioSink::new(&self.output);
+    }
+
+    fn music_finished(&self, channel: usize) -> bool {
+        self.sinks[channel].empty()
+    }
+}
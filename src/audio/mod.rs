@@ -0,0 +1,404 @@
+pub mod backend;
+
+use amethyst::{
+    core::Time,
+    ecs::{Read, ReaderId, System, SystemData, Write, WriteExpect},
+    prelude::*,
+    shrev::EventChannel,
+    ui::{UiEvent, UiEventType},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use crate::map::LevelInfo;
+use crate::settings::Settings;
+use backend::{AudioBackend, NullBackend, OutputBackend, RecordingBackend, MUSIC_CHANNELS};
+
+// Game events with a sound, written to `EventChannel<SoundEvent>`
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundEvent {
+    TowerFire,
+    Hit,
+    Death,
+    WaveStart,
+    // Enemy reached the goal
+    Leak,
+    UiClick,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeCategory {
+    Effects,
+    Ui,
+    Music,
+}
+
+impl Default for VolumeCategory {
+    fn default() -> Self {
+        VolumeCategory::Effects
+    }
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SoundInfo {
+    // One of the files is played for each event, in turn
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub category: VolumeCategory,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+// Every volume is from 0.0 to 1.0, master scales all categories. Kept in `Settings`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Volumes {
+    pub master: f32,
+    pub effects: f32,
+    pub ui: f32,
+    pub music: f32,
+}
+
+impl Default for Volumes {
+    fn default() -> Self {
+        Volumes {
+            master: 1.0,
+            effects: 0.8,
+            ui: 0.8,
+            music: 0.6,
+        }
+    }
+}
+
+impl Volumes {
+    pub fn get(&self, category: VolumeCategory) -> f32 {
+        let volume = match category {
+            VolumeCategory::Effects => self.effects,
+            VolumeCategory::Ui => self.ui,
+            VolumeCategory::Music => self.music,
+        };
+        (volume * self.master).max(0.0).min(1.0)
+    }
+}
+
+fn default_crossfade() -> f32 {
+    2.0
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct AudioInfo {
+    pub sounds: HashMap<SoundEvent, SoundInfo>,
+    // Seconds two music tracks play together when the track changes
+    #[serde(default = "default_crossfade")]
+    pub crossfade: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MusicTrack {
+    pub file: String,
+    // Seconds, the next track fades in before the end. Tracks without
+    // a length change only once the backend reports that they ended
+    #[serde(default)]
+    pub length: Option<f32>,
+}
+
+pub struct Audio {
+    pub backend: Box<dyn AudioBackend>,
+}
+
+// Plays the level playlist in a loop, crossfading between the two music channels
+#[derive(Default)]
+pub struct MusicPlayer {
+    playlist: Vec<MusicTrack>,
+    // Track playing on `channel`
+    current: Option<usize>,
+    channel: usize,
+    // Seconds the current track has played
+    position: f32,
+    // Seconds left of the crossfade, the other channel fades out meanwhile
+    fade: f32,
+    // Volumes last sent to the backend
+    channel_volumes: [f32; MUSIC_CHANNELS],
+    changed: bool,
+}
+
+impl MusicPlayer {
+    // Fades to the first track of the new playlist, an empty one fades the music out
+    pub fn set_playlist(&mut self, playlist: Vec<MusicTrack>) {
+        self.playlist = playlist;
+        self.changed = true;
+    }
+
+    fn start(&mut self, backend: &mut dyn AudioBackend, track: Option<usize>, crossfade: f32) {
+        let playing = self.current.is_some();
+        self.channel = (self.channel + 1) % MUSIC_CHANNELS;
+        self.current = track.filter(|x| *x < self.playlist.len());
+        self.position = 0.0;
+        self.fade = if playing { crossfade } else { 0.0 };
+        if playing && self.fade <= 0.0 {
+            backend.stop_music((self.channel + 1) % MUSIC_CHANNELS);
+        }
+
+        match self.current {
+            Some(i) => {
+                backend.play_music(self.channel, &self.playlist[i].file, 0.0);
+                self.channel_volumes[self.channel] = 0.0;
+            },
+            None => backend.stop_music(self.channel),
+        }
+    }
+
+    fn update(&mut self, backend: &mut dyn AudioBackend, delta: f32, crossfade: f32, volume: f32) {
+        if self.changed {
+            self.changed = false;
+            let first = if self.playlist.is_empty() { None } else { Some(0) };
+            self.start(backend, first, crossfade);
+        } else if let Some(i) = self.current {
+            self.position += delta;
+            let fading_in = self.playlist[i].length.map_or(false, |x| self.position >= x - crossfade);
+            if self.fade <= 0.0 && (fading_in || backend.music_finished(self.channel)) {
+                self.start(backend, Some((i + 1) % self.playlist.len()), crossfade);
+            }
+        }
+
+        let old_channel = (self.channel + 1) % MUSIC_CHANNELS;
+        let mut targets = [0.0; MUSIC_CHANNELS];
+        if self.fade > 0.0 {
+            self.fade -= delta;
+            let t = (1.0 - self.fade / crossfade).max(0.0).min(1.0);
+            targets[self.channel] = volume * t;
+            targets[old_channel] = volume * (1.0 - t);
+            if self.fade <= 0.0 {
+                backend.stop_music(old_channel);
+                targets[old_channel] = 0.0;
+            }
+        } else {
+            targets[self.channel] = volume;
+        }
+        if self.current.is_none() {
+            targets[self.channel] = 0.0;
+        }
+
+        for channel in 0..MUSIC_CHANNELS {
+            if (targets[channel] - self.channel_volumes[channel]).abs() > 0.001 {
+                backend.set_music_volume(channel, targets[channel]);
+                self.channel_volumes[channel] = targets[channel];
+            }
+        }
+    }
+}
+
+// `TD_AUDIO=null` plays nothing, `TD_AUDIO=record` prints what would be played
+fn create_backend() -> Box<dyn AudioBackend> {
+    match env::var("TD_AUDIO").as_ref().map(|x| x.as_str()) {
+        Ok("null") => return Box::new(NullBackend),
+        Ok("record") => return Box::new(RecordingBackend::default()),
+        _ => (),
+    }
+    match OutputBackend::new() {
+        Some(e) => Box::new(e),
+        None => {
+            println!("No audio output device, sound is off");
+            Box::new(NullBackend)
+        }
+    }
+}
+
+pub fn initialise_audio(world: &mut World) {
+    world.insert::<Audio>(Audio { backend: create_backend() });
+
+    let mut music_player = MusicPlayer::default();
+    music_player.set_playlist(world.fetch::<LevelInfo>().music.clone());
+    world.insert::<MusicPlayer>(music_player);
+}
+
+// Plays sounds of game events and UI clicks, and runs the music player
+#[derive(Default)]
+pub struct AudioSystem {
+    reader_id: Option<ReaderId<SoundEvent>>,
+    ui_reader_id: Option<ReaderId<UiEvent>>,
+    // Next file to play for sounds with several files
+    next_file: HashMap<SoundEvent, usize>,
+}
+
+impl<'s> System<'s> for AudioSystem {
+    type SystemData = (
+        Read<'s, EventChannel<SoundEvent>>,
+        Read<'s, EventChannel<UiEvent>>,
+        Read<'s, AudioInfo>,
+        Read<'s, Settings>,
+        Write<'s, MusicPlayer>,
+        Option<WriteExpect<'s, Audio>>,
+        Read<'s, Time>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<SoundEvent>>().register_reader());
+        self.ui_reader_id = Some(world.fetch_mut::<EventChannel<UiEvent>>().register_reader());
+    }
+
+    fn run(&mut self, (sound_events, ui_events, audio_info, settings, mut music_player, audio, time): Self::SystemData) {
+        let mut events = sound_events.read(self.reader_id.as_mut().unwrap()).copied().collect::<Vec<_>>();
+        for event in ui_events.read(self.ui_reader_id.as_mut().unwrap()) {
+            if event.event_type == UiEventType::Click {
+                events.push(SoundEvent::UiClick);
+            }
+        }
+        let mut audio = match audio {
+            Some(e) => e,
+            None => return,
+        };
+
+        // Many towers fire in the same frame, one sound of each event is enough
+        events.sort_by_key(|x| *x as usize);
+        events.dedup();
+        for event in events {
+            let sound = match audio_info.sounds.get(&event) {
+                Some(e) if !e.files.is_empty() => e,
+                _ => continue,
+            };
+            let index = self.next_file.entry(event).or_insert(0);
+            let file = &sound.files[*index % sound.files.len()];
+            *index += 1;
+            audio.backend.play_sound(file, sound.volume * settings.volumes.get(sound.category));
+        }
+
+        // Music keeps playing while the game is paused
+        music_player.update(
+            audio.backend.as_mut(),
+            time.delta_real_seconds(),
+            audio_info.crossfade,
+            settings.volumes.get(VolumeCategory::Music),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::ecs::RunNow;
+    use backend::AudioCall;
+    use std::sync::{Arc, Mutex};
+
+    fn track(file: &str, length: Option<f32>) -> MusicTrack {
+        MusicTrack {
+            file: file.to_string(),
+            length: length,
+        }
+    }
+
+    fn take_calls(calls: &Arc<Mutex<Vec<AudioCall>>>) -> Vec<AudioCall> {
+        calls.lock().unwrap().drain(..).collect()
+    }
+
+    #[test]
+    fn sound_events_play_once_per_frame_and_files_in_turn() {
+        let mut sounds = HashMap::new();
+        sounds.insert(SoundEvent::Hit, SoundInfo {
+            files: vec!["hit_1.wav".to_string(), "hit_2.wav".to_string()],
+            category: VolumeCategory::Effects,
+            volume: 0.5,
+        });
+        let backend = RecordingBackend::default();
+        let calls = backend.calls();
+
+        let mut world = World::new();
+        world.insert(AudioInfo { sounds: sounds, crossfade: 2.0 });
+        world.insert(Settings::default());
+        world.insert(Audio { backend: Box::new(backend) });
+        let mut system = AudioSystem::default();
+        System::setup(&mut system, &mut world);
+
+        {
+            let mut events = world.fetch_mut::<EventChannel<SoundEvent>>();
+            events.single_write(SoundEvent::Hit);
+            events.single_write(SoundEvent::Hit);
+            // No sound is set for it
+            events.single_write(SoundEvent::TowerFire);
+        }
+        system.run_now(&world);
+        world.fetch_mut::<EventChannel<SoundEvent>>().single_write(SoundEvent::Hit);
+        system.run_now(&world);
+
+        // Effects volume of the default settings is 0.8
+        assert_eq!(take_calls(&calls), vec![
+            AudioCall::PlaySound { file: "hit_1.wav".to_string(), volume: 0.4 },
+            AudioCall::PlaySound { file: "hit_2.wav".to_string(), volume: 0.4 },
+        ]);
+    }
+
+    #[test]
+    fn new_playlist_crossfades_from_the_old_track() {
+        let mut backend = RecordingBackend::default();
+        let calls = backend.calls();
+        let mut player = MusicPlayer::default();
+
+        player.set_playlist(vec![track("level_1.wav", None)]);
+        player.update(&mut backend, 0.1, 2.0, 1.0);
+        assert_eq!(take_calls(&calls), vec![
+            AudioCall::PlayMusic { channel: 1, file: "level_1.wav".to_string(), volume: 0.0 },
+            AudioCall::SetMusicVolume { channel: 1, volume: 1.0 },
+        ]);
+
+        player.set_playlist(vec![track("level_2.wav", None)]);
+        player.update(&mut backend, 0.5, 2.0, 1.0);
+        assert_eq!(take_calls(&calls), vec![
+            AudioCall::PlayMusic { channel: 0, file: "level_2.wav".to_string(), volume: 0.0 },
+            AudioCall::SetMusicVolume { channel: 0, volume: 0.25 },
+            AudioCall::SetMusicVolume { channel: 1, volume: 0.75 },
+        ]);
+
+        player.update(&mut backend, 1.5, 2.0, 1.0);
+        assert_eq!(take_calls(&calls), vec![
+            AudioCall::StopMusic { channel: 1 },
+            AudioCall::SetMusicVolume { channel: 0, volume: 1.0 },
+            AudioCall::SetMusicVolume { channel: 1, volume: 0.0 },
+        ]);
+    }
+
+    #[test]
+    fn next_track_fades_in_before_the_end() {
+        let mut backend = RecordingBackend::default();
+        let calls = backend.calls();
+        let mut player = MusicPlayer::default();
+
+        player.set_playlist(vec![track("a.wav", Some(4.0)), track("b.wav", Some(4.0))]);
+        player.update(&mut backend, 0.0, 1.0, 0.5);
+        take_calls(&calls);
+
+        player.update(&mut backend, 2.5, 1.0, 0.5);
+        assert!(take_calls(&calls).is_empty());
+
+        player.update(&mut backend, 0.5, 1.0, 0.5);
+        assert_eq!(take_calls(&calls), vec![
+            AudioCall::PlayMusic { channel: 0, file: "b.wav".to_string(), volume: 0.0 },
+            AudioCall::SetMusicVolume { channel: 0, volume: 0.25 },
+            AudioCall::SetMusicVolume { channel: 1, volume: 0.25 },
+        ]);
+    }
+
+    #[test]
+    fn empty_playlist_fades_the_music_out() {
+        let mut backend = RecordingBackend::default();
+        let calls = backend.calls();
+        let mut player = MusicPlayer::default();
+
+        player.set_playlist(vec![track("a.wav", None)]);
+        player.update(&mut backend, 0.0, 2.0, 1.0);
+        take_calls(&calls);
+
+        player.set_playlist(Vec::new());
+        player.update(&mut backend, 1.0, 2.0, 1.0);
+        assert_eq!(take_calls(&calls), vec![
+            AudioCall::StopMusic { channel: 0 },
+            AudioCall::SetMusicVolume { channel: 1, volume: 0.5 },
+        ]);
+    }
+}
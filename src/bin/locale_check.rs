@@ -0,0 +1,94 @@
+// Reports strings missing from the string tables
+// cargo run --bin locale_check
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::process;
+use td_amethyst::locale::strings::{missing_keys, StringTable};
+
+const LOCALE_DIR: &str = "assets/locale";
+const DEFAULT_LANGUAGE: &str = "en";
+// Literal keys are found after these, keys built at runtime are not checked
+const KEY_MARKERS: [&str; 4] = [".tr(\"", ".tr_with(\"", ".tr_count(\"", "\"@"];
+
+fn collect_files(dir: &Path, extension: &str, files: &mut Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            collect_files(&path, extension, files);
+        } else if path.extension().map_or(false, |x| x == extension) {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+}
+
+// Keys used in code, UI prefabs and level scripts
+fn used_keys() -> BTreeSet<(String, String)> {
+    let mut files = Vec::new();
+    collect_files(Path::new("src"), "rs", &mut files);
+    collect_files(Path::new("assets/ui"), "ron", &mut files);
+    collect_files(Path::new("assets/levels"), "rhai", &mut files);
+
+    let mut keys = BTreeSet::new();
+    for file in files {
+        let data = fs::read_to_string(&file).unwrap_or_default();
+        for marker in KEY_MARKERS.iter() {
+            for (start, _) in data.match_indices(marker) {
+                let rest = &data[start + marker.len()..];
+                if let Some(end) = rest.find('"') {
+                    keys.insert((rest[..end].to_string(), file.clone()));
+                }
+            }
+        }
+    }
+    keys
+}
+
+fn main() {
+    let reference = match StringTable::load(&format!("{}/{}.json", LOCALE_DIR, DEFAULT_LANGUAGE)) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    let mut failed = false;
+
+    for (key, file) in used_keys() {
+        // Skips the markers themselves in this file
+        if key.is_empty() || key.contains('{') {
+            continue;
+        }
+        if !reference.strings.contains_key(&key) {
+            println!("{}: {} is not in {}.json", file, key, DEFAULT_LANGUAGE);
+            failed = true;
+        }
+    }
+
+    let mut languages = Vec::new();
+    collect_files(Path::new(LOCALE_DIR), "json", &mut languages);
+    languages.sort();
+    for path in languages {
+        let table = match StringTable::load(&path) {
+            Ok(e) => e,
+            Err(e) => {
+                println!("{}", e);
+                failed = true;
+                continue;
+            }
+        };
+        let missing = missing_keys(&reference, &table);
+        if !missing.is_empty() {
+            println!("{} misses {} strings: {}", path, missing.len(), missing.join(", "));
+            failed = true;
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+    println!("All strings are translated");
+}
@@ -0,0 +1,89 @@
+// Generates a level map and its level info
+// cargo run --bin mapgen -- --seed 7 --width 40 --height 40 --spawns 3 --name 2
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use td_amethyst::mapgen::{generate, to_level_info_json, to_tiled_json, MapParams};
+
+fn usage() -> ! {
+    println!("Usage: mapgen [--seed N] [--width N] [--height N] [--spawns N] [--decorations F] [--template FILE] [--name NAME] [--out DIR]");
+    process::exit(1);
+}
+
+fn parse<T: std::str::FromStr>(name: &str, value: Option<String>) -> T {
+    match value.map(|x| x.parse::<T>()) {
+        Some(Ok(e)) => e,
+        _ => {
+            println!("Bad value for {}", name);
+            usage();
+        }
+    }
+}
+
+fn main() {
+    let mut params = MapParams::default();
+    let mut name = "generated".to_string();
+    let mut out = PathBuf::from("assets/levels");
+    // Waves and economy of the new level are copied from it
+    let mut template_path = PathBuf::from("assets/levels/1_info.json");
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => params.seed = parse(&arg, args.next()),
+            "--width" => params.width = parse(&arg, args.next()),
+            "--height" => params.height = parse(&arg, args.next()),
+            "--spawns" => params.spawns = parse(&arg, args.next()),
+            "--decorations" => params.decoration_density = parse(&arg, args.next()),
+            "--template" => template_path = parse(&arg, args.next()),
+            "--name" => name = parse(&arg, args.next()),
+            "--out" => out = parse(&arg, args.next()),
+            _ => usage(),
+        }
+    }
+
+    let template = match fs::read_to_string(&template_path).map(|x| serde_json::from_str::<Value>(&x)) {
+        Ok(Ok(e)) => e,
+        Ok(Err(e)) => {
+            println!("Failed to parse {}: {}", template_path.display(), e);
+            process::exit(1);
+        }
+        Err(e) => {
+            println!("Can't read {}: {}", template_path.display(), e);
+            process::exit(1);
+        }
+    };
+
+    let level = match generate(&params) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't generate map: {}", e);
+            process::exit(1);
+        }
+    };
+    let level_info = match to_level_info_json(&level, &template) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't use {}: {}", template_path.display(), e);
+            process::exit(1);
+        }
+    };
+
+    // Same naming as the shipped level: <name>_<width>_<height>.json and <name>_info.json
+    let map_path = out.join(format!("{}_{}_{}.json", name, params.width, params.height));
+    let info_path = out.join(format!("{}_info.json", name));
+    let files = [
+        (&map_path, to_tiled_json(&level, "../../../tile1.tsx")),
+        (&info_path, level_info),
+    ];
+    for (path, value) in files.iter() {
+        let data = serde_json::to_string_pretty(value).unwrap();
+        if let Err(e) = fs::write(path, data) {
+            println!("Can't write {}: {}", path.display(), e);
+            process::exit(1);
+        }
+        println!("Wrote {}", path.display());
+    }
+}
@@ -0,0 +1,85 @@
+// Compares tower range queries on the spatial grid with a plain loop over all units.
+// Grid update is paid once per frame, queries once per tower
+// cargo run --release --bin spatial_bench
+use td_amethyst::spatial::grid::SpatialGrid;
+use std::time::{Duration, Instant};
+
+const TILE: f32 = 32.0;
+const MAP_TILES: f32 = 40.0;
+const FRAMES: u32 = 100;
+
+struct Random(u32);
+
+impl Random {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 % 10000) as f32 / 10000.0
+    }
+}
+
+fn run(units: usize, tower_count: usize) -> (Duration, Duration, Duration, usize) {
+    let half = TILE * MAP_TILES * 0.5;
+    let mut random = Random(0x9E37_79B9);
+    let mut positions = (0..units)
+        .map(|_| (random.next() * half * 2.0 - half, random.next() * half * 2.0 - half))
+        .collect::<Vec<_>>();
+    let towers = (0..tower_count)
+        .map(|_| (random.next() * half * 2.0 - half, random.next() * half * 2.0 - half, 64.0 + random.next() * 96.0))
+        .collect::<Vec<_>>();
+
+    let mut grid = SpatialGrid::<u32>::new((-half, -half), (TILE, TILE), MAP_TILES as usize, MAP_TILES as usize);
+    let mut update_time = Duration::default();
+    let mut query_time = Duration::default();
+    let mut loop_time = Duration::default();
+    let mut hits = 0;
+
+    for _ in 0..FRAMES {
+        for position in positions.iter_mut() {
+            position.0 = (position.0 + random.next() * 4.0 - 2.0).max(-half).min(half);
+            position.1 = (position.1 + random.next() * 4.0 - 2.0).max(-half).min(half);
+        }
+
+        let start = Instant::now();
+        for (i, (x, y)) in positions.iter().enumerate() {
+            grid.insert(i as u32, *x, *y);
+        }
+        update_time += start.elapsed();
+
+        let start = Instant::now();
+        let mut grid_hits = 0;
+        for (x, y, range) in towers.iter() {
+            grid_hits += grid.query_radius(*x, *y, *range).len();
+        }
+        query_time += start.elapsed();
+
+        // Every tower checks every unit
+        let start = Instant::now();
+        let mut loop_hits = 0;
+        for (x, y, range) in towers.iter() {
+            loop_hits += positions.iter()
+                .filter(|(ux, uy)| (ux - x) * (ux - x) + (uy - y) * (uy - y) <= range * range)
+                .count();
+        }
+        loop_time += start.elapsed();
+
+        assert_eq!(grid_hits, loop_hits);
+        hits += grid_hits;
+    }
+    (update_time / FRAMES, query_time / FRAMES, loop_time / FRAMES, hits / FRAMES as usize)
+}
+
+fn main() {
+    println!("{} frames on a {}x{} tile map, times per frame", FRAMES, MAP_TILES, MAP_TILES);
+    println!("{:>8} {:>8} {:>12} {:>12} {:>12} {:>8}", "units", "towers", "grid update", "grid query", "loop", "hits");
+    for towers in [50, 200].iter() {
+        for units in [100, 1000, 5000, 10000, 50000].iter() {
+            let (update_time, query_time, loop_time, hits) = run(*units, *towers);
+            println!(
+                "{:>8} {:>8} {:>12?} {:>12?} {:>12?} {:>8}",
+                units, towers, update_time, query_time, loop_time, hits,
+            );
+        }
+    }
+}
@@ -1,13 +1,12 @@
 use amethyst::{
     prelude::*,
-    utils::application_root_dir,
     ecs::{
         Join, Read, ReadStorage, System, WriteStorage, Write,
-        Entities, ReadExpect, SystemData,
+        Entities, Entity, ReadExpect, SystemData,
     },
     core::{
-        geometry::Plane, Transform, Named,
-        math::{Point2, Vector2, Vector3},
+        geometry::Plane, Transform, Named, Time, Hidden,
+        math::{Point2, Point3, Vector2, Vector3},
     },
     derive::SystemDesc,
     assets::{AssetStorage},
@@ -15,36 +14,30 @@ use amethyst::{
         camera::{ActiveCamera, Camera},
         sprite::{SpriteRender, SpriteSheet},
     },
-    ui::{UiFinder, UiText},
+    ui::{Interactable, UiFinder, UiText, UiTransform},
     window::ScreenDimensions,
 };
-use std::fs::File;
-use amethyst_input::VirtualKeyCode;
-use amethyst_tiles::{MortonEncoder, TileMap, Map};
+use amethyst_tiles::{MortonEncoder2D, TileMap, Map};
 use crate::map::{BlockTile, MapData};
-use winit::MouseButton;
+use crate::camera_director::CameraDirector;
+use crate::utils::sprite_size;
+use crate::spatial::{SpatialIndex, PICK_MARGIN};
+use crate::settings::Settings;
 
 use amethyst::input::{InputHandler, StringBindings};
-use amethyst_window::{DisplayConfig};
 
 
-pub fn initialise_camera(world: &mut World) {
-    use ron::de::from_reader;
-
-    let app_root = application_root_dir().unwrap();
-
-    let display_config_path = app_root.join("config").join("display.ron");
-    let display_conf_file = File::open(&display_config_path).expect("Failed opening file");
-
-    let display_config: DisplayConfig = match from_reader(display_conf_file) {
-        Ok(e) => e,
-        Err(e) => {
-            println!("Failed to load display config: {}", e);
-            std::process::exit(1);
-        }
-    };
+// Camera projection for the current window size, keeps the same zoom on any DPI
+pub fn screen_camera(screen_dimensions: &ScreenDimensions) -> Camera {
+    let hidpi = screen_dimensions.hidpi_factor() as f32;
+    Camera::standard_2d(
+        screen_dimensions.width() / hidpi * PROJECTION_SCALE,
+        screen_dimensions.height() / hidpi * PROJECTION_SCALE,
+    )
+}
 
-    let dimensions = display_config.dimensions.unwrap();
+pub fn initialise_camera(world: &mut World) {
+    let camera = screen_camera(&world.read_resource::<ScreenDimensions>());
 
     let mut transform = Transform::default();
     transform.set_translation_xyz(0.0, 0.0, 1.0);
@@ -52,13 +45,52 @@ pub fn initialise_camera(world: &mut World) {
 
     world
         .create_entity()
-        .with(Camera::standard_2d(dimensions.0 as f32 * 0.5, dimensions.1 as f32 * 0.5))
+        .with(camera)
         .with(transform)
         .build();
 }
 
-#[derive(Default)]
-pub struct CameraSystem;
+// World units per logical pixel with camera scale 1.0
+pub const PROJECTION_SCALE: f32 = 0.5;
+
+// World units per physical screen pixel, mouse and screen sizes are in physical pixels
+pub fn world_per_pixel(screen_dimensions: &ScreenDimensions, scale: f32) -> f32 {
+    PROJECTION_SCALE * scale / screen_dimensions.hidpi_factor() as f32
+}
+
+// Keeps the camera center so the view doesn't leave the map
+pub fn clamp_to_map(x: f32, y: f32, scale: f32, map_data: &MapData, screen_dimensions: &ScreenDimensions) -> (f32, f32) {
+    let map_half_width = (map_data.width * map_data.tilewidth) as f32 * 0.5;
+    let map_half_height = (map_data.height * map_data.tileheight) as f32 * 0.5;
+    let view_half_width = screen_dimensions.width() * world_per_pixel(screen_dimensions, scale) * 0.5;
+    let view_half_height = screen_dimensions.height() * world_per_pixel(screen_dimensions, scale) * 0.5;
+
+    // Center the map when the view is bigger than the map
+    let clamp = |value: f32, map_half: f32, view_half: f32| {
+        if view_half >= map_half { 0.0 } else { value.max(view_half - map_half).min(map_half - view_half) }
+    };
+    (clamp(x, map_half_width, view_half_width), clamp(y, map_half_height, view_half_height))
+}
+
+pub struct CameraSystem {
+    // World units per second
+    pub move_speed: f32,
+    // Pixels from the window border where edge scrolling starts
+    pub edge_size: f32,
+    pub edge_scrolling: bool,
+    last_mouse_position: Option<(f32, f32)>,
+}
+
+impl Default for CameraSystem {
+    fn default() -> Self {
+        CameraSystem {
+            move_speed: 600.0,
+            edge_size: 10.0,
+            edge_scrolling: true,
+            last_mouse_position: None,
+        }
+    }
+}
 
 impl<'s> System<'s> for CameraSystem {
     type SystemData = (
@@ -67,46 +99,143 @@ impl<'s> System<'s> for CameraSystem {
         ReadStorage<'s, Camera>,
         WriteStorage<'s, Transform>,
         Read<'s, InputHandler<StringBindings>>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, MapData>,
+        Read<'s, Time>,
+        Write<'s, CameraDirector>,
+        Read<'s, Settings>,
     );
 
-    fn run(&mut self, (active_camera, entities, cameras, mut transforms, input): Self::SystemData) {
-        let multiplayer = match input.key_is_down(VirtualKeyCode::LShift) {
+    fn run(
+        &mut self,
+        (
+            active_camera,
+            entities,
+            cameras,
+            mut transforms,
+            input,
+            screen_dimensions,
+            map_data,
+            time,
+            mut director,
+            settings,
+        ): Self::SystemData,
+    ) {
+        self.move_speed = settings.camera_speed;
+        self.edge_scrolling = settings.edge_scrolling;
+
+        let multiplayer = match input.action_is_down("camera_boost").unwrap_or(false) {
             true => 2.0,
             false => 1.0,
         };
-        let y_move = input.axis_value("updown").unwrap();
-        let x_move = input.axis_value("leftright").unwrap();
+        let mut y_move = input.axis_value("updown").unwrap();
+        let mut x_move = input.axis_value("leftright").unwrap();
         let scrool = input.axis_value("scrool").unwrap();
 
-        if x_move != 0.0 || y_move != 0.0 || scrool != 0.0 {
-            let mut camera_join = (&cameras, &mut transforms).join();
-            if let Some((_, camera_transform)) = active_camera
-                .entity
-                .and_then(|a| camera_join.get(a, &entities))
-                .or_else(|| camera_join.next())
-            {
-                let camera_move_speed = 10.0;
+        let mouse_position = input.mouse_position();
+        let last_mouse_position = self.last_mouse_position;
+        self.last_mouse_position = mouse_position;
 
-                camera_transform.prepend_translation_x(x_move * camera_move_speed * multiplayer);
-                camera_transform.prepend_translation_y(y_move * camera_move_speed * multiplayer);
+        let (width, height) = (screen_dimensions.width(), screen_dimensions.height());
 
-                let z_scale = 0.3 * scrool;
-                let scale = camera_transform.scale();
+        if let (Some((mouse_x, mouse_y)), true) = (mouse_position, self.edge_scrolling) {
+            if mouse_x <= self.edge_size { x_move = -1.0; }
+            if mouse_x >= width - self.edge_size { x_move = 1.0; }
+            if mouse_y <= self.edge_size { y_move = 1.0; }
+            if mouse_y >= height - self.edge_size { y_move = -1.0; }
+        }
 
-                let max_scale = 1.0;
-                let min_scale = 2.5;
+        let mut camera_join = (&cameras, &mut transforms).join();
+        if let Some((_, camera_transform)) = active_camera
+            .entity
+            .and_then(|a| camera_join.get(a, &entities))
+            .or_else(|| camera_join.next())
+        {
+            let old_scale = camera_transform.scale().x;
+            let mut x = camera_transform.translation().x;
+            let mut y = camera_transform.translation().y;
 
-                let scale = Vector3::new(
-                    (scale.x + z_scale).max(max_scale).min(min_scale),
-                    (scale.y + z_scale).max(max_scale).min(min_scale),
-                    (scale.z + z_scale).max(max_scale).min(min_scale),
-                );
-                camera_transform.set_scale(scale);
+            let step = self.move_speed * multiplayer * time.delta_real_seconds();
+            x += x_move * step;
+            y += y_move * step;
+
+            // Drag the map while `camera_drag` is held
+            let dragging = input.action_is_down("camera_drag").unwrap_or(false);
+            if let (true, Some(mouse), Some(last_mouse)) = (dragging, mouse_position, last_mouse_position) {
+                x -= (mouse.0 - last_mouse.0) * world_per_pixel(&screen_dimensions, old_scale);
+                y += (mouse.1 - last_mouse.1) * world_per_pixel(&screen_dimensions, old_scale);
+            }
+
+            // Manual input takes over scripted camera moves
+            if x_move != 0.0 || y_move != 0.0 || scrool != 0.0 || dragging {
+                director.cancel();
+            }
+
+            let max_scale = 1.0;
+            let min_scale = 2.5;
+            let scale = (old_scale + 0.3 * scrool).max(max_scale).min(min_scale);
+
+            // Keep the world point under the cursor in place while zooming
+            if let (true, Some(mouse)) = (scale != old_scale, mouse_position) {
+                let offset_x = mouse.0 - width * 0.5;
+                let offset_y = height * 0.5 - mouse.1;
+                x += offset_x * world_per_pixel(&screen_dimensions, old_scale - scale);
+                y += offset_y * world_per_pixel(&screen_dimensions, old_scale - scale);
             }
+
+            let (x, y) = clamp_to_map(x, y, scale, &map_data, &screen_dimensions);
+            camera_transform.set_translation_x(x);
+            camera_transform.set_translation_y(y);
+            camera_transform.set_scale(Vector3::new(scale, scale, scale));
+        }
+    }
+}
+
+// Rebuilds the camera projection when the window is resized or moved to a screen with other DPI
+#[derive(Default)]
+pub struct CameraResizeSystem {
+    last_dimensions: (f32, f32, f64),
+}
+
+impl<'s> System<'s> for CameraResizeSystem {
+    type SystemData = (
+        WriteStorage<'s, Camera>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, MapData>,
+        WriteStorage<'s, Transform>,
+    );
+
+    fn run(&mut self, (mut cameras, screen_dimensions, map_data, mut transforms): Self::SystemData) {
+        let dimensions = (screen_dimensions.width(), screen_dimensions.height(), screen_dimensions.hidpi_factor());
+        if dimensions == self.last_dimensions {
+            return;
+        }
+        self.last_dimensions = dimensions;
+
+        for (camera, transform) in (&mut cameras, &mut transforms).join() {
+            *camera = screen_camera(&screen_dimensions);
+
+            // Bigger window may show area outside of the map
+            let (x, y) = clamp_to_map(
+                transform.translation().x, transform.translation().y, transform.scale().x, &map_data, &screen_dimensions,
+            );
+            transform.set_translation_x(x);
+            transform.set_translation_y(y);
         }
     }
 }
 
+// Mouse position in the world, updated every frame by `MouseRaycastSystem`
+#[derive(Default)]
+pub struct CursorPosition {
+    pub world: Option<Point3<f32>>,
+    pub tile: Option<Point3<u32>>,
+    // Mouse is over a visible ui element, world position is not set in this case
+    pub over_ui: bool,
+    // Named sprite under the mouse, the one drawn on top wins
+    pub hovered: Option<Entity>,
+}
+
 #[derive(SystemDesc)]
 pub struct MouseRaycastSystem;
 
@@ -117,14 +246,18 @@ impl<'s> System<'s> for MouseRaycastSystem {
         ReadStorage<'s, Camera>,
         ReadStorage<'s, SpriteRender>,
         ReadStorage<'s, Named>,
-        WriteStorage<'s, UiText>,
+        ReadStorage<'s, UiText>,
+        ReadStorage<'s, UiTransform>,
+        ReadStorage<'s, Interactable>,
+        ReadStorage<'s, Hidden>,
         Read<'s, AssetStorage<SpriteSheet>>,
         ReadExpect<'s, ScreenDimensions>,
         Read<'s, ActiveCamera>,
         Read<'s, InputHandler<StringBindings>>,
         UiFinder<'s>,
-        WriteStorage<'s, TileMap<BlockTile, MortonEncoder>>,
-        Write<'s, MapData>,
+        WriteStorage<'s, TileMap<BlockTile, MortonEncoder2D>>,
+        Write<'s, CursorPosition>,
+        Read<'s, SpatialIndex>,
     );
 
     fn run(
@@ -135,18 +268,38 @@ impl<'s> System<'s> for MouseRaycastSystem {
             cameras,
             sprites,
             names,
-            _ui_texts,
+            ui_texts,
+            ui_transforms,
+            interactables,
+            hiddens,
             sprite_sheets,
             screen_dimensions,
             active_camera,
             input,
             _ui_finder,
             mut tilemaps,
-            map_data,
+            mut cursor,
+            spatial_index,
         ): Self::SystemData,
     ) {
+        cursor.world = None;
+        cursor.tile = None;
+        cursor.over_ui = false;
+        cursor.hovered = None;
+
         // Get the mouse position if its available
         if let Some(mouse_position) = input.mouse_position() {
+            // Ui uses the bottom left corner as origin
+            let ui_y = screen_dimensions.height() - mouse_position.1;
+            // Labels with empty text are not shown
+            cursor.over_ui = (&ui_transforms, &interactables, ui_texts.maybe(), !&hiddens).join()
+                .any(|(ui_transform, _, ui_text, _)| {
+                    ui_text.map_or(true, |x| !x.text.is_empty()) && ui_transform.position_inside(mouse_position.0, ui_y)
+                });
+            if cursor.over_ui {
+                return;
+            }
+
             // Get the active camera if it is spawned and ready
             let mut camera_join = (&cameras, &transforms).join();
             if let Some((camera, camera_transform)) = active_camera
@@ -162,48 +315,53 @@ impl<'s> System<'s> for MouseRaycastSystem {
                 );
                 let distance = ray.intersect_plane(&Plane::with_z(0.0)).unwrap();
                 let mouse_world_position = ray.at_distance(distance);
+                cursor.world = Some(mouse_world_position);
 
                 // TileMap click
                 for tilemap in (&mut tilemaps).join() {
                     let pos = Vector3::new(mouse_world_position.x, mouse_world_position.y, 2.0);
                     match tilemap.to_tile(&pos, None) {
                         Ok(p) => {
-                            if input.mouse_button_is_down(MouseButton::Left) {
-                                let id_point = map_data.get_id_in_point(p);
-                                let string_id = match id_point {
-                                    Some(e) => format!("{}", e), None => "None".to_string()
-                                };
-                                println!("{} {}", pos, string_id);
-
-                                // map_data.change_id_on_point(p, 30);
-                            }
+                            cursor.tile = Some(p);
                         },
                         Err(_e) => (),
                     }
                 }
 
                 // Find any sprites which the mouse is currently inside
-                let mut _found_name = None;
-                for (sprite, transform, name) in (&sprites, &transforms, &names).join() {
-                    let sprite_sheet = sprite_sheets.get(&sprite.sprite_sheet).unwrap();
-                    let sprite = &sprite_sheet.sprites[sprite.sprite_number];
+                let mut top_z = std::f32::MIN;
+                let (x, y) = (mouse_world_position.x, mouse_world_position.y);
+                let candidates = spatial_index.query_rect(
+                    &entities, (x - PICK_MARGIN, y - PICK_MARGIN), (x + PICK_MARGIN, y + PICK_MARGIN),
+                );
+                for entity in candidates {
+                    let (sprite, transform) = match (sprites.get(entity), transforms.get(entity)) {
+                        (Some(s), Some(t)) if names.contains(entity) && !hiddens.contains(entity) => (s, t),
+                        _ => continue,
+                    };
+                    let (width, height) = match sprite_size(&sprite_sheets, sprite) {
+                        Some(e) => e,
+                        None => continue,
+                    };
                     let (min_x, max_x, min_y, max_y) = {
                         // Sprites are centered on a coordinate, so we build out a bbox for the sprite coordinate
                         // and dimensions
                         // Notice we ignore z-axis for this example.
                         (
-                            transform.translation().x - (sprite.width * 0.5),
-                            transform.translation().x + (sprite.width * 0.5),
-                            transform.translation().y - (sprite.height * 0.5),
-                            transform.translation().y + (sprite.height * 0.5),
+                            transform.translation().x - (width * 0.5),
+                            transform.translation().x + (width * 0.5),
+                            transform.translation().y - (height * 0.5),
+                            transform.translation().y + (height * 0.5),
                         )
                     };
                     if mouse_world_position.x > min_x
                         && mouse_world_position.x < max_x
                         && mouse_world_position.y > min_y
                         && mouse_world_position.y < max_y
+                        && transform.translation().z >= top_z
                     {
-                        _found_name = Some(&name.name);
+                        top_z = transform.translation().z;
+                        cursor.hovered = Some(entity);
                     }
                 }
             }
@@ -0,0 +1,232 @@
+use amethyst::{
+    ecs::{Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, Write, WriteStorage},
+    core::{math::Vector3, Time, Transform},
+    renderer::camera::{ActiveCamera, Camera},
+    window::ScreenDimensions,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use crate::camera::clamp_to_map;
+use crate::map::MapData;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Default for Easing {
+    fn default() -> Self {
+        Easing::EaseInOut
+    }
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 { 2.0 * t * t } else { -1.0 + (4.0 - 2.0 * t) * t }
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum CameraTarget {
+    Point(f32, f32),
+    Entity(Entity),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraMove {
+    pub target: CameraTarget,
+    // Camera scale at the end of the move, current scale is kept when not set
+    pub scale: Option<f32>,
+    // Seconds
+    pub duration: f32,
+    // Seconds to stay at the target before the next move starts
+    pub hold: f32,
+    pub easing: Easing,
+}
+
+// Camera move of the level intro, listed as `intro` in the level info
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IntroMove {
+    // Tile to fly to, the map centre when not set
+    #[serde(default)]
+    pub tile: Option<Vec<u32>>,
+    #[serde(default)]
+    pub scale: Option<f32>,
+    pub duration: f32,
+    #[serde(default)]
+    pub hold: f32,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+struct ActiveMove {
+    camera_move: CameraMove,
+    from: (f32, f32, f32),
+    elapsed: f32,
+}
+
+// Scripted camera moves, any manual camera input cancels them
+#[derive(Default)]
+pub struct CameraDirector {
+    queue: VecDeque<CameraMove>,
+    active: Option<ActiveMove>,
+    follow: Option<Entity>,
+}
+
+impl CameraDirector {
+    pub fn move_to(&mut self, x: f32, y: f32, scale: Option<f32>, duration: f32, hold: f32) {
+        self.queue.push_back(CameraMove {
+            target: CameraTarget::Point(x, y),
+            scale: scale,
+            duration: duration,
+            hold: hold,
+            easing: Easing::EaseInOut,
+        });
+    }
+
+    pub fn move_to_entity(&mut self, entity: Entity, scale: Option<f32>, duration: f32, hold: f32) {
+        self.queue.push_back(CameraMove {
+            target: CameraTarget::Entity(entity),
+            scale: scale,
+            duration: duration,
+            hold: hold,
+            easing: Easing::EaseInOut,
+        });
+    }
+
+    pub fn push(&mut self, camera_move: CameraMove) {
+        self.queue.push_back(camera_move);
+    }
+
+    pub fn follow(&mut self, entity: Option<Entity>) {
+        self.follow = entity;
+    }
+
+    pub fn following(&self) -> Option<Entity> {
+        self.follow
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.active.is_some() || !self.queue.is_empty()
+    }
+
+    // Called on manual camera input
+    pub fn cancel(&mut self) {
+        self.queue.clear();
+        self.active = None;
+        self.follow = None;
+    }
+}
+
+fn target_position(target: CameraTarget, transforms: &WriteStorage<'_, Transform>) -> Option<(f32, f32)> {
+    match target {
+        CameraTarget::Point(x, y) => Some((x, y)),
+        CameraTarget::Entity(entity) => transforms.get(entity)
+            .map(|t| (t.translation().x, t.translation().y)),
+    }
+}
+
+#[derive(Default)]
+pub struct CameraDirectorSystem;
+
+impl<'s> System<'s> for CameraDirectorSystem {
+    type SystemData = (
+        Read<'s, ActiveCamera>,
+        Entities<'s>,
+        ReadStorage<'s, Camera>,
+        WriteStorage<'s, Transform>,
+        Write<'s, CameraDirector>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, MapData>,
+        Read<'s, Time>,
+    );
+
+    fn run(
+        &mut self,
+        (active_camera, entities, cameras, mut transforms, mut director, screen_dimensions, map_data, time): Self::SystemData,
+    ) {
+        let camera_entity = match active_camera.entity
+            .or_else(|| (&entities, &cameras).join().next().map(|(e, _)| e))
+        {
+            Some(e) => e,
+            None => return,
+        };
+        let (x, y, scale) = match transforms.get(camera_entity) {
+            Some(t) => (t.translation().x, t.translation().y, t.scale().x),
+            None => return,
+        };
+
+        if director.active.is_none() {
+            if let Some(camera_move) = director.queue.pop_front() {
+                director.active = Some(ActiveMove {
+                    camera_move: camera_move,
+                    from: (x, y, scale),
+                    elapsed: 0.0,
+                });
+            }
+        }
+
+        let mut position = None;
+        let mut finished = false;
+        if let Some(active) = director.active.as_mut() {
+            active.elapsed += time.delta_real_seconds();
+            let camera_move = active.camera_move;
+
+            match target_position(camera_move.target, &transforms) {
+                Some((target_x, target_y)) => {
+                    let t = if camera_move.duration > 0.0 {
+                        camera_move.easing.apply(active.elapsed / camera_move.duration)
+                    } else {
+                        1.0
+                    };
+                    let (from_x, from_y, from_scale) = active.from;
+                    let target_scale = camera_move.scale.unwrap_or(from_scale);
+                    position = Some((
+                        from_x + (target_x - from_x) * t,
+                        from_y + (target_y - from_y) * t,
+                        from_scale + (target_scale - from_scale) * t,
+                    ));
+                    finished = active.elapsed >= camera_move.duration + camera_move.hold;
+                },
+                // Target entity is gone
+                None => finished = true,
+            }
+        } else if let Some(entity) = director.follow {
+            match transforms.get(entity) {
+                Some(t) => {
+                    // Smoothly catch up with the followed entity
+                    let k = (time.delta_real_seconds() * 5.0).min(1.0);
+                    position = Some((
+                        x + (t.translation().x - x) * k,
+                        y + (t.translation().y - y) * k,
+                        scale,
+                    ));
+                },
+                None => director.follow = None,
+            }
+        }
+        if finished {
+            director.active = None;
+        }
+
+        if let Some((x, y, scale)) = position {
+            let (x, y) = clamp_to_map(x, y, scale, &map_data, &screen_dimensions);
+            let camera_transform = transforms.get_mut(camera_entity).unwrap();
+            camera_transform.set_translation_x(x);
+            camera_transform.set_translation_y(y);
+            camera_transform.set_scale(Vector3::new(scale, scale, scale));
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use crate::unit::UnitType;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageType {
+    // Reduced by flat `armour` and by resistance
+    Physical,
+    // Ignores `armour`, reduced only by resistance
+    Pierce,
+    Magic,
+    Fire,
+    Poison,
+}
+
+impl Default for DamageType {
+    fn default() -> Self {
+        DamageType::Physical
+    }
+}
+
+// Damage which a unit of `unit_type` actually takes from a hit.
+// Every damage source must use this function, so all resistances work the same way.
+// `damage_taken_multiplier` comes from status effects of the unit.
+pub fn calculate_damage(amount: f32, damage_type: DamageType, unit_type: &UnitType, damage_taken_multiplier: f32) -> f32 {
+    // Negative resistance is a weakness, resistance 1.0 is immunity
+    let resistance = unit_type.resistances.get(&damage_type).cloned().unwrap_or(0.0).min(1.0);
+    let mut damage = amount * (1.0 - resistance);
+
+    if damage_type == DamageType::Physical {
+        damage -= unit_type.armour;
+    }
+    (damage * damage_taken_multiplier).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn unit_type(armour: f32, resistances: &[(DamageType, f32)]) -> UnitType {
+        UnitType {
+            armour: armour,
+            resistances: resistances.iter().cloned().collect::<HashMap<_, _>>(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resistance_reduces_damage() {
+        let unit = unit_type(0.0, &[(DamageType::Fire, 0.25)]);
+        assert_eq!(calculate_damage(100.0, DamageType::Fire, &unit, 1.0), 75.0);
+        assert_eq!(calculate_damage(100.0, DamageType::Magic, &unit, 1.0), 100.0);
+    }
+
+    #[test]
+    fn negative_resistance_is_a_weakness() {
+        let unit = unit_type(0.0, &[(DamageType::Fire, -0.5)]);
+        assert_eq!(calculate_damage(100.0, DamageType::Fire, &unit, 1.0), 150.0);
+    }
+
+    #[test]
+    fn resistance_is_capped_at_immunity() {
+        let unit = unit_type(0.0, &[(DamageType::Poison, 1.0), (DamageType::Magic, 2.0)]);
+        assert_eq!(calculate_damage(100.0, DamageType::Poison, &unit, 1.0), 0.0);
+        assert_eq!(calculate_damage(100.0, DamageType::Magic, &unit, 1.0), 0.0);
+    }
+
+    #[test]
+    fn armour_applies_to_physical_only() {
+        let unit = unit_type(5.0, &[]);
+        assert_eq!(calculate_damage(20.0, DamageType::Physical, &unit, 1.0), 15.0);
+        assert_eq!(calculate_damage(20.0, DamageType::Pierce, &unit, 1.0), 20.0);
+        assert_eq!(calculate_damage(20.0, DamageType::Fire, &unit, 1.0), 20.0);
+    }
+
+    #[test]
+    fn armour_applies_after_resistance() {
+        let unit = unit_type(5.0, &[(DamageType::Physical, 0.5)]);
+        assert_eq!(calculate_damage(20.0, DamageType::Physical, &unit, 1.0), 5.0);
+    }
+
+    #[test]
+    fn damage_taken_multiplier_scales_damage() {
+        let unit = unit_type(5.0, &[]);
+        assert_eq!(calculate_damage(20.0, DamageType::Physical, &unit, 2.0), 30.0);
+        assert_eq!(calculate_damage(20.0, DamageType::Magic, &unit, 0.5), 10.0);
+    }
+
+    #[test]
+    fn damage_is_never_negative() {
+        let unit = unit_type(50.0, &[]);
+        assert_eq!(calculate_damage(10.0, DamageType::Physical, &unit, 1.0), 0.0);
+        assert_eq!(calculate_damage(10.0, DamageType::Physical, &unit, 3.0), 0.0);
+    }
+}
@@ -0,0 +1,120 @@
+use amethyst::{
+    prelude::*,
+    ui::{UiFinder, UiText},
+};
+use serde::{Deserialize, Serialize};
+use crate::economy::{initialise_economy, Reason, TransactionLog};
+use crate::unit::UnitType;
+use crate::locale::Locale;
+use crate::stats::{write_save, SaveData};
+
+// Preset which takes its values from the save file
+pub const CUSTOM_DIFFICULTY: &str = "custom";
+
+fn default_multiplier() -> f32 {
+    1.0
+}
+
+// Multipliers applied to level and unit data, 1.0 keeps the authored value
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Difficulty {
+    pub name: String,
+    #[serde(default = "default_multiplier")]
+    pub health: f32,
+    #[serde(default = "default_multiplier")]
+    pub speed: f32,
+    // Scales `LevelInfo::spawn_speed`, bigger values spawn slower
+    #[serde(default = "default_multiplier")]
+    pub spawn_interval: f32,
+    #[serde(default = "default_multiplier")]
+    pub bounty: f32,
+    #[serde(default = "default_multiplier")]
+    pub starting_money: f32,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty {
+            name: "normal".to_string(),
+            health: 1.0,
+            speed: 1.0,
+            spawn_interval: 1.0,
+            bounty: 1.0,
+            starting_money: 1.0,
+        }
+    }
+}
+
+impl Difficulty {
+    // Unit type as it is spawned with this difficulty
+    pub fn apply_unit(&self, unit_type: &UnitType) -> UnitType {
+        let mut unit_type = unit_type.clone();
+        unit_type.max_health *= self.health;
+        unit_type.speed *= self.speed;
+        unit_type.bounty *= self.bounty;
+        unit_type
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct DifficultyPresets {
+    pub presets: Vec<Difficulty>,
+    // Preset used when the game starts
+    #[serde(default)]
+    pub selected: usize,
+}
+
+// Uses the preset kept in `SaveData`, must run after it is loaded
+pub fn initialise_difficulty(world: &mut World) {
+    let difficulty = {
+        let save = world.fetch::<SaveData>();
+        let mut presets = world.write_resource::<DifficultyPresets>();
+        if let Some(i) = presets.presets.iter().position(|x| x.name == save.difficulty) {
+            presets.selected = i;
+        }
+        let difficulty = presets.presets.get(presets.selected).cloned().unwrap_or_default();
+        if difficulty.name == CUSTOM_DIFFICULTY {
+            Difficulty { name: difficulty.name, ..save.custom_difficulty.clone() }
+        } else {
+            difficulty
+        }
+    };
+    world.insert::<Difficulty>(difficulty);
+    update_difficulty_text(world);
+}
+
+pub fn update_difficulty_text(world: &World) {
+    let text = {
+        let locale = world.fetch::<Locale>();
+        let name = locale.data_name("difficulty", &world.fetch::<Difficulty>().name);
+        locale.tr_with("menu.difficulty", &[("name", name)])
+    };
+    let entity = world.exec(|ui_finder: UiFinder<'_>| { ui_finder.find("difficulty") });
+    let mut ui_texts = world.write_storage::<UiText>();
+    if let Some(ui_text) = entity.and_then(|e| ui_texts.get_mut(e)) {
+        ui_text.text = text;
+    }
+}
+
+// Switches to the next preset, only possible before any money was spent
+pub fn select_next_difficulty(world: &mut World) {
+    let spent = world.fetch::<TransactionLog>().entries.iter().any(|x| x.reason != Reason::StartingMoney);
+    if spent {
+        println!("Difficulty can be changed only before building");
+        return;
+    }
+
+    {
+        let mut presets = world.write_resource::<DifficultyPresets>();
+        if presets.presets.is_empty() {
+            return;
+        }
+        presets.selected = (presets.selected + 1) % presets.presets.len();
+        let mut save = world.write_resource::<SaveData>();
+        save.difficulty = presets.presets[presets.selected].name.clone();
+        write_save(&save);
+    }
+    initialise_difficulty(world);
+    // Starting money depends on the difficulty
+    initialise_economy(world);
+}
@@ -0,0 +1,94 @@
+use amethyst::{
+    ecs::{Read, ReadExpect, System, Write},
+    input::{InputHandler, StringBindings},
+    window::Window,
+    winit::dpi::LogicalSize,
+};
+use serde::{Deserialize, Serialize};
+use crate::settings::Settings;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMode {
+    Windowed,
+    Fullscreen,
+    // Maximized window without decorations
+    Borderless,
+}
+
+impl Default for WindowMode {
+    fn default() -> Self {
+        WindowMode::Windowed
+    }
+}
+
+impl WindowMode {
+    pub fn next(&self) -> WindowMode {
+        match self {
+            WindowMode::Windowed => WindowMode::Fullscreen,
+            WindowMode::Fullscreen => WindowMode::Borderless,
+            WindowMode::Borderless => WindowMode::Windowed,
+        }
+    }
+}
+
+// Camera follows the new size through `CameraResizeSystem`
+pub fn apply_window_mode(window: &Window, mode: WindowMode) {
+    match mode {
+        WindowMode::Windowed => {
+            window.set_fullscreen(None);
+            window.set_decorations(true);
+            window.set_maximized(false);
+        },
+        WindowMode::Fullscreen => {
+            window.set_fullscreen(Some(window.get_current_monitor()));
+        },
+        WindowMode::Borderless => {
+            window.set_fullscreen(None);
+            window.set_decorations(false);
+            window.set_maximized(true);
+        },
+    }
+}
+
+// Resolution is used only by the windowed mode
+pub fn apply_window_settings(window: &Window, mode: WindowMode, resolution: Option<(u32, u32)>) {
+    apply_window_mode(window, mode);
+    if let (WindowMode::Windowed, Some((width, height))) = (mode, resolution) {
+        window.set_inner_size(LogicalSize::new(width as f64, height as f64));
+    }
+}
+
+// Switches window modes with `window_mode` action and applies window changes from `Settings`
+#[derive(Default)]
+pub struct WindowModeSystem {
+    // Mode and resolution the window has now, set by `initialise_settings` first
+    applied: Option<(WindowMode, Option<(u32, u32)>)>,
+    key_down: bool,
+}
+
+impl<'s> System<'s> for WindowModeSystem {
+    type SystemData = (
+        ReadExpect<'s, Window>,
+        Read<'s, InputHandler<StringBindings>>,
+        Write<'s, Settings>,
+    );
+
+    fn run(&mut self, (window, input, mut settings): Self::SystemData) {
+        let key_down = input.action_is_down("window_mode").unwrap_or(false);
+        if key_down && !self.key_down {
+            settings.window_mode = settings.window_mode.next();
+        }
+        self.key_down = key_down;
+
+        let wanted = (settings.window_mode, settings.resolution);
+        match self.applied {
+            None => self.applied = Some(wanted),
+            Some(e) if e != wanted => {
+                apply_window_settings(&window, wanted.0, wanted.1);
+                self.applied = Some(wanted);
+            },
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use amethyst::{
+    prelude::*,
+    core::Time,
+    ecs::{Read, ReadExpect, System, WriteStorage},
+    ui::{UiFinder, UiText},
+};
+use crate::difficulty::Difficulty;
+use crate::map::LevelInfo;
+use crate::UserData;
+use crate::locale::Locale;
+use crate::script::ScriptMessage;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Reason {
+    StartingMoney,
+    Bounty,
+    WaveIncome,
+    Interest,
+    Build,
+    Upgrade,
+    Sell,
+    // Given by a level script
+    Script,
+}
+
+impl Reason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Reason::StartingMoney => "starting money",
+            Reason::Bounty => "bounty",
+            Reason::WaveIncome => "wave income",
+            Reason::Interest => "interest",
+            Reason::Build => "build",
+            Reason::Upgrade => "upgrade",
+            Reason::Sell => "sell",
+            Reason::Script => "script",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Transaction {
+    // Positive for credits, negative for debits
+    pub amount: f32,
+    pub reason: Reason,
+    pub wave: u32,
+    // Seconds since the game start
+    pub time: f32,
+}
+
+// Every change of `UserData::money` with its reason
+#[derive(Default)]
+pub struct TransactionLog {
+    pub entries: Vec<Transaction>,
+    // Wave written into new entries
+    pub wave: u32,
+}
+
+impl TransactionLog {
+    pub fn total(&self, reason: Reason) -> f32 {
+        self.entries.iter().filter(|x| x.reason == reason).map(|x| x.amount).sum()
+    }
+
+    // Totals by reason in the order of the first transaction
+    pub fn totals(&self) -> Vec<(Reason, f32)> {
+        let mut totals = Vec::<(Reason, f32)>::new();
+        for entry in self.entries.iter() {
+            match totals.iter_mut().find(|x| x.0 == entry.reason) {
+                Some(e) => e.1 += entry.amount,
+                None => totals.push((entry.reason, entry.amount)),
+            }
+        }
+        totals
+    }
+}
+
+pub fn credit(user_data: &mut UserData, log: &mut TransactionLog, time: &Time, amount: f32, reason: Reason) {
+    if amount <= 0.0 {
+        return;
+    }
+    user_data.money += amount;
+    log.entries.push(Transaction {
+        amount: amount,
+        reason: reason,
+        wave: log.wave,
+        time: time.absolute_time_seconds() as f32,
+    });
+}
+
+// Returns false and changes nothing when there is not enough money
+pub fn debit(user_data: &mut UserData, log: &mut TransactionLog, time: &Time, amount: f32, reason: Reason) -> bool {
+    if user_data.money < amount {
+        return false;
+    }
+    user_data.money -= amount;
+    log.entries.push(Transaction {
+        amount: -amount,
+        reason: reason,
+        wave: log.wave,
+        time: time.absolute_time_seconds() as f32,
+    });
+    true
+}
+
+// Sets money and lives of the loaded level
+pub fn initialise_economy(world: &mut World) {
+    let (starting_money, lives) = {
+        let level_info = world.fetch::<LevelInfo>();
+        (level_info.starting_money * world.fetch::<Difficulty>().starting_money, level_info.lives)
+    };
+
+    let mut user_data = UserData { money: 0.0, lives: lives };
+    let mut log = TransactionLog::default();
+    credit(&mut user_data, &mut log, &world.fetch::<Time>(), starting_money, Reason::StartingMoney);
+
+    world.insert::<UserData>(user_data);
+    world.insert::<TransactionLog>(log);
+}
+
+// Income and interest paid when a wave is cleared
+pub fn pay_wave_income(world: &mut World) {
+    let (income, rate, cap) = {
+        let level_info = world.fetch::<LevelInfo>();
+        (level_info.wave_income, level_info.interest, level_info.interest_cap)
+    };
+    let time = world.fetch::<Time>();
+    let mut user_data = world.write_resource::<UserData>();
+    let mut log = world.write_resource::<TransactionLog>();
+
+    // Interest is counted from the money banked before the income
+    let interest = (user_data.money * rate).floor();
+    let interest = cap.map_or(interest, |x| interest.min(x));
+    credit(&mut user_data, &mut log, &time, income, Reason::WaveIncome);
+    credit(&mut user_data, &mut log, &time, interest, Reason::Interest);
+}
+
+// Shows money, lives and the last transaction
+#[derive(Default)]
+pub struct EconomyHudSystem;
+
+impl<'s> System<'s> for EconomyHudSystem {
+    type SystemData = (
+        WriteStorage<'s, UiText>,
+        UiFinder<'s>,
+        ReadExpect<'s, UserData>,
+        Read<'s, TransactionLog>,
+        Read<'s, ScriptMessage>,
+        Read<'s, Time>,
+        Read<'s, Locale>,
+    );
+
+    fn run(&mut self, (mut ui_texts, ui_finder, user_data, log, script_message, time, locale): Self::SystemData) {
+        if let Some(ui_text) = ui_finder.find("top-resources").and_then(|e| ui_texts.get_mut(e)) {
+            let lives = locale.tr_count("hud.lives", user_data.lives as i64, &[]);
+            ui_text.text = locale.tr_with("hud.resources", &[("money", user_data.money.floor().to_string()), ("lives", lives)]);
+        }
+
+        // Messages of the level script are shown over transactions until they expire,
+        // the last transaction stays for a few seconds
+        let now = time.absolute_time_seconds() as f32;
+        let message = if now < script_message.until {
+            script_message.text.clone()
+        } else {
+            log.entries.last()
+                .filter(|x| now - x.time < 3.0)
+                .map_or("".to_string(), |x| locale.tr_with("hud.transaction", &[
+                    ("amount", format!("{:+.0}", x.amount)),
+                    ("reason", locale.data_name("reason", &x.reason.name().replace(' ', "_"))),
+                ]))
+        };
+        if let Some(ui_text) = ui_finder.find("top-message").and_then(|e| ui_texts.get_mut(e)) {
+            ui_text.text = message;
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use amethyst::{
+    prelude::*,
+    core::{transform::{Parent, Transform}, Time},
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Entity, Join, LazyUpdate, Read, ReadStorage,
+        System, WriteStorage,
+    },
+    renderer::SpriteRender,
+    core::math::Vector3,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use crate::unit::{SpriteData, Unit};
+use crate::damage::{calculate_damage, DamageType};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Stacking {
+    // Applying again only restarts the duration
+    Refresh,
+    // Applying again adds a stack up to `max_stacks` and restarts the duration
+    Stack,
+    // Applying again does nothing while the effect is active
+    Ignore,
+}
+
+impl Default for Stacking {
+    fn default() -> Self {
+        Stacking::Refresh
+    }
+}
+
+fn default_multiplier() -> f32 {
+    1.0
+}
+
+fn default_max_stacks() -> u32 {
+    1
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct EffectType {
+    pub name: String,
+    pub sprite_name: String,
+    pub sprite_id: u32,
+    // Seconds
+    pub duration: f32,
+    #[serde(default)]
+    pub stacking: Stacking,
+    #[serde(default = "default_max_stacks")]
+    pub max_stacks: u32,
+    // Damage per stack dealt every `tick_interval` seconds
+    #[serde(default)]
+    pub tick_damage: f32,
+    #[serde(default)]
+    pub tick_interval: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    // Multipliers are applied once per stack
+    #[serde(default = "default_multiplier")]
+    pub speed_multiplier: f32,
+    #[serde(default = "default_multiplier")]
+    pub damage_taken_multiplier: f32,
+    #[serde(default)]
+    pub stun: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct EffectTypes {
+    pub types: Vec<EffectType>,
+}
+
+impl EffectTypes {
+    pub fn get(&self, name: &str) -> Option<&EffectType> {
+        self.types.iter().find(|&x| x.name == name)
+    }
+}
+
+#[derive(Clone)]
+pub struct ActiveEffect {
+    pub effect_type: EffectType,
+    pub stacks: u32,
+    pub remaining: f32,
+    pub tick_timer: f32,
+}
+
+#[derive(Default)]
+pub struct StatusEffects {
+    pub active: Vec<ActiveEffect>,
+}
+
+impl Component for StatusEffects {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect_type: &EffectType) {
+        match self.active.iter_mut().find(|x| x.effect_type.name == effect_type.name) {
+            Some(active) => match effect_type.stacking {
+                Stacking::Refresh => active.remaining = effect_type.duration,
+                Stacking::Stack => {
+                    active.stacks = (active.stacks + 1).min(effect_type.max_stacks);
+                    active.remaining = effect_type.duration;
+                },
+                Stacking::Ignore => (),
+            },
+            None => self.active.push(ActiveEffect {
+                effect_type: effect_type.clone(),
+                stacks: 1,
+                remaining: effect_type.duration,
+                tick_timer: effect_type.tick_interval,
+            }),
+        }
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.active.iter().any(|x| x.effect_type.name == name)
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.active.iter().any(|x| x.effect_type.stun) {
+            return 0.0;
+        }
+        self.active.iter()
+            .map(|x| x.effect_type.speed_multiplier.powi(x.stacks as i32))
+            .product()
+    }
+
+    pub fn damage_taken_multiplier(&self) -> f32 {
+        self.active.iter()
+            .map(|x| x.effect_type.damage_taken_multiplier.powi(x.stacks as i32))
+            .product()
+    }
+}
+
+// Small sprite shown above a unit while the effect is active
+pub struct EffectIcon {
+    pub unit: Entity,
+    pub effect: String,
+}
+
+impl Component for EffectIcon {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn load_effect_info(world: &mut World) {
+    world.register::<StatusEffects>();
+    world.register::<EffectIcon>();
+}
+
+#[derive(Default)]
+pub struct StatusEffectSystem;
+
+impl<'s> System<'s> for StatusEffectSystem {
+    type SystemData = (
+        WriteStorage<'s, StatusEffects>,
+        WriteStorage<'s, Unit>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (mut status_effects, mut units, time): Self::SystemData) {
+        let delta = time.delta_seconds();
+        for (effects, unit) in (&mut status_effects, &mut units).join() {
+            let damage_taken_multiplier = effects.damage_taken_multiplier();
+            for effect in effects.active.iter_mut() {
+                effect.remaining -= delta;
+
+                if effect.effect_type.tick_interval > 0.0 {
+                    effect.tick_timer -= delta;
+                    while effect.tick_timer <= 0.0 {
+                        let damage = calculate_damage(
+                            effect.effect_type.tick_damage * effect.stacks as f32,
+                            effect.effect_type.damage_type,
+                            &unit.unit_type,
+                            damage_taken_multiplier,
+                        );
+                        unit.take_damage(damage);
+                        effect.tick_timer += effect.effect_type.tick_interval;
+                    }
+                }
+            }
+            effects.active.retain(|x| x.remaining > 0.0);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct EffectIconSystem;
+
+impl<'s> System<'s> for EffectIconSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, StatusEffects>,
+        ReadStorage<'s, EffectIcon>,
+        WriteStorage<'s, Transform>,
+        Read<'s, SpriteData>,
+        Read<'s, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, status_effects, icons, mut transforms, sprite_data, lazy_update): Self::SystemData,
+    ) {
+        let icon_size = 10.0;
+        let mut shown = HashSet::<(Entity, String)>::new();
+
+        // Drop icons of expired effects and line up the rest above the unit
+        for (entity, icon) in (&entities, &icons).join() {
+            let index = status_effects.get(icon.unit)
+                .and_then(|x| x.active.iter().position(|e| e.effect_type.name == icon.effect));
+            match index {
+                Some(i) if entities.is_alive(icon.unit) => {
+                    if let Some(transform) = transforms.get_mut(entity) {
+                        transform.set_translation_xyz(i as f32 * icon_size - 8.0, 20.0, 0.1);
+                    }
+                    shown.insert((icon.unit, icon.effect.clone()));
+                },
+                _ => {
+                    entities.delete(entity).unwrap();
+                },
+            }
+        }
+
+        for (entity, effects) in (&entities, &status_effects).join() {
+            for (i, effect) in effects.active.iter().enumerate() {
+                if shown.contains(&(entity, effect.effect_type.name.clone())) {
+                    continue;
+                }
+                let sprite_sheet = match sprite_data.sprite_handles.get(&effect.effect_type.sprite_name) {
+                    Some(e) => e.clone(),
+                    None => continue,
+                };
+
+                let mut transform = Transform::default();
+                transform.set_translation_xyz(i as f32 * icon_size - 8.0, 20.0, 0.1);
+                transform.set_scale(Vector3::new(0.3, 0.3, 1.0));
+
+                lazy_update
+                    .create_entity(&entities)
+                    .with(SpriteRender {
+                        sprite_sheet: sprite_sheet,
+                        sprite_number: effect.effect_type.sprite_id as usize,
+                    })
+                    .with(transform)
+                    .with(Parent::new(entity))
+                    .with(EffectIcon {
+                        unit: entity,
+                        effect: effect.effect_type.name.clone(),
+                    })
+                    .build();
+            }
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use amethyst::{
+    prelude::*,
+    ui::{UiFinder, UiText},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::map::LevelInfo;
+use crate::unit::{UnitTyes, UnitType};
+use crate::locale::Locale;
+use td_amethyst::random::SeededRandom;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct EndlessConfig {
+    // Budget of the first generated wave, spent on `UnitType::cost`
+    pub budget: f32,
+    // Budget multiplier for every next wave
+    pub budget_growth: f32,
+    pub max_units: u32,
+    // Chance of picking an elite variant, grows by `elite_chance_growth` each wave
+    pub elite_chance: f32,
+    pub elite_chance_growth: f32,
+    pub elite_chance_max: f32,
+    // Elite variants multiply health, bounty and cost of the base type
+    pub elite_health: f32,
+    pub elite_bounty: f32,
+    pub elite_cost: f32,
+    // Same seed gives the same waves, a random seed is used when not set
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+// Endless mode state, waves after the authored ones are generated into `LevelInfo::levels`
+#[derive(Default)]
+pub struct EndlessMode {
+    pub enabled: bool,
+    pub seed: u64,
+    // Number of waves written in the level file
+    pub authored_waves: usize,
+}
+
+pub fn elite_name(name: &str) -> String {
+    format!("elite {}", name)
+}
+
+fn elite_type(unit_type: &UnitType, config: &EndlessConfig) -> UnitType {
+    let mut elite = unit_type.clone();
+    elite.name = elite_name(&unit_type.name);
+    elite.elite = true;
+    elite.max_health *= config.elite_health;
+    elite.bounty *= config.elite_bounty;
+    elite.cost *= config.elite_cost;
+    elite
+}
+
+pub fn initialise_endless(world: &mut World) {
+    // Elite variants are normal unit types, so they also can be used in level files
+    {
+        let config = world.fetch::<EndlessConfig>();
+        let mut unit_types = world.write_resource::<UnitTyes>();
+        let elites = unit_types.types.iter()
+            .filter(|x| x.cost > 0.0 && !x.elite)
+            .map(|x| elite_type(x, &config))
+            .collect::<Vec<_>>();
+        unit_types.types.extend(elites);
+    }
+
+    let seed = world.fetch::<EndlessConfig>().seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |x| x.as_nanos() as u64)
+    });
+    let authored_waves = world.fetch::<LevelInfo>().levels.len();
+    world.insert::<EndlessMode>(EndlessMode {
+        enabled: false,
+        seed: seed,
+        authored_waves: authored_waves,
+    });
+    update_endless_text(world);
+}
+
+pub fn update_endless_text(world: &World) {
+    let text = {
+        let endless = world.fetch::<EndlessMode>();
+        let locale = world.fetch::<Locale>();
+        if endless.enabled {
+            locale.tr_with("menu.endless_on", &[("seed", endless.seed.to_string())])
+        } else {
+            locale.tr("menu.endless_off")
+        }
+    };
+    let entity = world.exec(|ui_finder: UiFinder<'_>| { ui_finder.find("endless") });
+    let mut ui_texts = world.write_storage::<UiText>();
+    if let Some(ui_text) = entity.and_then(|e| ui_texts.get_mut(e)) {
+        ui_text.text = text;
+    }
+}
+
+pub fn toggle_endless(world: &mut World) {
+    {
+        let mut endless = world.write_resource::<EndlessMode>();
+        endless.enabled = !endless.enabled;
+        // Drop waves generated by an earlier endless run
+        world.write_resource::<LevelInfo>().levels.truncate(endless.authored_waves);
+    }
+    update_endless_text(world);
+}
+
+// Units of generated wave `round`, 0 is the first wave after the authored ones
+pub fn generate_wave(unit_types: &[UnitType], config: &EndlessConfig, seed: u64, round: u32) -> HashMap<String, u32> {
+    // Every wave has its own generator, so a wave doesn't depend on the ones before it
+    let mut random = SeededRandom::new(seed ^ (round as u64).wrapping_mul(0xA24B_AED4_963E_E407));
+    let mut budget = config.budget * config.budget_growth.powi(round as i32);
+    let elite_chance = (config.elite_chance + config.elite_chance_growth * round as f32).min(config.elite_chance_max);
+
+    let base_types = unit_types.iter()
+        .filter(|x| x.cost > 0.0 && !x.elite)
+        .collect::<Vec<_>>();
+    let mut wave = HashMap::<String, u32>::new();
+    let mut units = 0;
+
+    while units < config.max_units {
+        let affordable = base_types.iter()
+            .filter(|x| x.cost <= budget)
+            .collect::<Vec<_>>();
+        if affordable.is_empty() {
+            break;
+        }
+        let unit_type = affordable[(random.next_f32() * affordable.len() as f32) as usize];
+
+        let elite_cost = unit_type.cost * config.elite_cost;
+        let (name, cost) = if random.next_f32() < elite_chance && elite_cost <= budget {
+            (elite_name(&unit_type.name), elite_cost)
+        } else {
+            (unit_type.name.clone(), unit_type.cost)
+        };
+
+        budget -= cost;
+        units += 1;
+        *wave.entry(name).or_insert(0) += 1;
+    }
+    wave
+}
+
+// Makes sure `LevelInfo::levels` has the wave, returns false when the level has no such wave
+pub fn ensure_wave(world: &World, wave: u32) -> bool {
+    if (wave as usize) < world.fetch::<LevelInfo>().levels.len() {
+        return true;
+    }
+    let (enabled, seed, authored_waves) = {
+        let endless = world.fetch::<EndlessMode>();
+        (endless.enabled, endless.seed, endless.authored_waves)
+    };
+    if !enabled {
+        return false;
+    }
+
+    let mut level_info = world.write_resource::<LevelInfo>();
+    while level_info.levels.len() <= wave as usize {
+        let round = (level_info.levels.len() - authored_waves) as u32;
+        let generated = generate_wave(
+            &world.fetch::<UnitTyes>().types, &world.fetch::<EndlessConfig>(), seed, round,
+        );
+        if generated.is_empty() {
+            println!("Endless wave {} has no units, check unit costs and the endless budget", round);
+            return false;
+        }
+        level_info.levels.push(generated);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EndlessConfig {
+        EndlessConfig {
+            budget: 20.0,
+            budget_growth: 1.2,
+            max_units: 40,
+            elite_chance: 0.1,
+            elite_chance_growth: 0.05,
+            elite_chance_max: 0.3,
+            elite_health: 2.0,
+            elite_bounty: 2.0,
+            elite_cost: 2.5,
+            seed: None,
+        }
+    }
+
+    fn unit_types(config: &EndlessConfig) -> Vec<UnitType> {
+        let base = [("bug", 1.0), ("rat", 1.5), ("ogre", 40.0), ("tower guard", 0.0)].iter()
+            .map(|(name, cost)| UnitType {
+                name: name.to_string(),
+                cost: *cost,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let elites = base.iter().filter(|x| x.cost > 0.0).map(|x| elite_type(x, config)).collect::<Vec<_>>();
+        base.into_iter().chain(elites).collect()
+    }
+
+    fn wave_cost(wave: &HashMap<String, u32>, unit_types: &[UnitType]) -> f32 {
+        wave.iter()
+            .map(|(name, count)| unit_types.iter().find(|x| &x.name == name).unwrap().cost * *count as f32)
+            .sum()
+    }
+
+    #[test]
+    fn same_seed_and_round_give_the_same_wave() {
+        let config = config();
+        let unit_types = unit_types(&config);
+        for round in 0..10 {
+            assert_eq!(generate_wave(&unit_types, &config, 42, round), generate_wave(&unit_types, &config, 42, round));
+        }
+    }
+
+    #[test]
+    fn wave_stays_in_the_budget() {
+        let config = config();
+        let unit_types = unit_types(&config);
+        for seed in 0..20 {
+            for round in 0..15 {
+                let wave = generate_wave(&unit_types, &config, seed, round);
+                let budget = config.budget * config.budget_growth.powi(round as i32);
+                assert!(wave_cost(&wave, &unit_types) <= budget + 0.001);
+                assert!(wave.values().sum::<u32>() <= config.max_units);
+                assert!(!wave.contains_key("tower guard"));
+            }
+        }
+    }
+
+    #[test]
+    fn elite_chance_is_capped() {
+        let mut config = config();
+        config.elite_chance = 0.0;
+        config.elite_chance_growth = 0.5;
+        config.elite_chance_max = 0.0;
+        let unit_types = unit_types(&config);
+        for round in 0..20 {
+            let wave = generate_wave(&unit_types, &config, 7, round);
+            assert!(wave.keys().all(|x| !unit_types.iter().any(|t| &t.name == x && t.elite)));
+        }
+
+        config.elite_chance_max = 1.0;
+        let wave = generate_wave(&unit_types, &config, 7, 10);
+        assert!(wave.keys().any(|x| unit_types.iter().any(|t| &t.name == x && t.elite)));
+    }
+
+    #[test]
+    fn elites_are_not_made_into_elites_again() {
+        let config = config();
+        let unit_types = unit_types(&config);
+        for round in 0..10 {
+            let wave = generate_wave(&unit_types, &config, 3, round);
+            assert!(wave.keys().all(|x| !x.starts_with("elite elite")));
+        }
+    }
+}
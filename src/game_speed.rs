@@ -0,0 +1,84 @@
+use amethyst::{
+    core::Time,
+    ecs::{Read, System, Write},
+    input::{InputHandler, StringBindings},
+};
+
+// Speeds selectable with `speed_up`, pause keeps the selected one
+pub const GAME_SPEEDS: [f32; 3] = [1.0, 2.0, 4.0];
+
+// Scale of simulation time, camera and UI use real time and are not affected
+pub struct GameSpeed {
+    pub paused: bool,
+    pub multiplier: f32,
+}
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        GameSpeed {
+            paused: false,
+            multiplier: 1.0,
+        }
+    }
+}
+
+impl GameSpeed {
+    pub fn time_scale(&self) -> f32 {
+        if self.paused { 0.0 } else { self.multiplier }
+    }
+
+    pub fn set(&mut self, multiplier: f32) {
+        self.paused = false;
+        self.multiplier = multiplier;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn speed_up(&mut self) {
+        let next = GAME_SPEEDS.iter()
+            .position(|x| *x == self.multiplier)
+            .map_or(0, |i| (i + 1) % GAME_SPEEDS.len());
+        self.set(GAME_SPEEDS[next]);
+    }
+}
+
+// Handles speed actions and applies the speed to `Time`
+#[derive(Default)]
+pub struct GameSpeedSystem {
+    // Actions held down in the last frame
+    down: Vec<&'static str>,
+}
+
+impl<'s> System<'s> for GameSpeedSystem {
+    type SystemData = (
+        Write<'s, GameSpeed>,
+        Write<'s, Time>,
+        Read<'s, InputHandler<StringBindings>>,
+    );
+
+    fn run(&mut self, (mut game_speed, mut time, input): Self::SystemData) {
+        let actions = ["pause", "speed_up", "speed_1", "speed_2", "speed_4"];
+        let down = actions.iter()
+            .filter(|a| input.action_is_down(**a).unwrap_or(false))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for action in down.iter().filter(|a| !self.down.contains(*a)) {
+            match *action {
+                "pause" => game_speed.toggle_pause(),
+                "speed_up" => game_speed.speed_up(),
+                "speed_1" => game_speed.set(1.0),
+                "speed_2" => game_speed.set(2.0),
+                "speed_4" => game_speed.set(4.0),
+                _ => (),
+            }
+        }
+        self.down = down;
+
+        if time.time_scale() != game_speed.time_scale() {
+            time.set_time_scale(game_speed.time_scale());
+        }
+    }
+}
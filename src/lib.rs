@@ -0,0 +1,9 @@
+// Engine free parts of the game, used by the tools in src/bin
+pub mod locale {
+    pub mod strings;
+}
+pub mod mapgen;
+pub mod random;
+pub mod spatial {
+    pub mod grid;
+}
@@ -0,0 +1,171 @@
+use amethyst::{
+    ecs::{Component, DenseVecStorage, Entities, Join, Read, System, WriteStorage},
+    prelude::*,
+    ui::UiText,
+};
+use std::fs;
+use crate::settings::Settings;
+use td_amethyst::locale::strings::{format, missing_keys, plural_category, Entry, StringTable};
+
+pub const LOCALE_DIR: &str = "assets/locale";
+// Every key has a text in this language, others fall back to it
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+// UI text starting with this is a key, it is replaced by `LocalizeSystem`
+pub const KEY_PREFIX: char = '@';
+
+fn table_path(language: &str) -> String {
+    format!("{}/{}.json", LOCALE_DIR, language)
+}
+
+// Strings of the selected language
+#[derive(Default)]
+pub struct Locale {
+    pub language: String,
+    table: StringTable,
+    fallback: StringTable,
+}
+
+impl Locale {
+    // Unknown languages fall back to the default one
+    pub fn load(language: &str) -> Locale {
+        let fallback = match StringTable::load(&table_path(DEFAULT_LANGUAGE)) {
+            Ok(e) => e,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        if language == DEFAULT_LANGUAGE {
+            return Locale {
+                language: language.to_string(),
+                table: StringTable::default(),
+                fallback: fallback,
+            };
+        }
+
+        let table = match StringTable::load(&table_path(language)) {
+            Ok(e) => e,
+            Err(e) => {
+                println!("{}", e);
+                return Locale::load(DEFAULT_LANGUAGE);
+            }
+        };
+        let missing = missing_keys(&fallback, &table);
+        if !missing.is_empty() {
+            println!("Missing {} strings in {}: {}", language, table_path(language), missing.join(", "));
+        }
+        Locale {
+            language: language.to_string(),
+            table: table,
+            fallback: fallback,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        if self.table.name.is_empty() { &self.fallback.name } else { &self.table.name }
+    }
+
+    fn entry(&self, key: &str) -> Option<&Entry> {
+        self.table.strings.get(key).or_else(|| self.fallback.strings.get(key))
+    }
+
+    // The key itself is shown for unknown keys
+    pub fn tr(&self, key: &str) -> String {
+        self.tr_with(key, &[])
+    }
+
+    pub fn tr_with(&self, key: &str, args: &[(&str, String)]) -> String {
+        match self.entry(key) {
+            Some(Entry::Text(e)) => format(e, args),
+            Some(Entry::Plural(e)) => format(e.get("other").map_or(key, |x| x.as_str()), args),
+            None => key.to_string(),
+        }
+    }
+
+    // Picks the plural form for `count`, which is also passed as `{count}`
+    pub fn tr_count(&self, key: &str, count: i64, args: &[(&str, String)]) -> String {
+        let mut args = args.to_vec();
+        args.push(("count", count.to_string()));
+        let forms = match self.entry(key) {
+            Some(Entry::Plural(e)) => e,
+            _ => return self.tr_with(key, &args),
+        };
+        let form = forms.get(plural_category(&self.language, count)).or_else(|| forms.get("other"));
+        format(form.map_or(key, |x| x.as_str()), &args)
+    }
+
+    // Names from data files (units, towers, effects...) are shown as they are when not translated
+    pub fn data_name(&self, kind: &str, name: &str) -> String {
+        let key = format!("{}.{}", kind, name);
+        if self.entry(&key).is_some() { self.tr(&key) } else { name.to_string() }
+    }
+}
+
+// Languages with a string table, sorted
+pub fn available_languages() -> Vec<String> {
+    let mut languages = fs::read_dir(LOCALE_DIR)
+        .map(|dir| {
+            dir.filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|e| e.extension().map_or(false, |x| x == "json"))
+                .filter_map(|e| Some(e.file_stem()?.to_str()?.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    languages.sort();
+    languages
+}
+
+// UI text which is set from a key, it is translated again when the language changes
+pub struct Localized {
+    pub key: String,
+}
+
+impl Component for Localized {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialise_locale(world: &mut World) {
+    world.register::<Localized>();
+    let language = world.fetch::<Settings>().language.clone();
+    world.insert::<Locale>(Locale::load(&language));
+}
+
+// Texts set by code are refreshed by whoever sets them, `Localized` texts change here
+pub fn set_language(world: &mut World, language: &str) {
+    world.insert::<Locale>(Locale::load(language));
+    world.write_resource::<Settings>().language = language.to_string();
+}
+
+// Turns `@key` texts into `Localized` ones and translates them again after a language change
+#[derive(Default)]
+pub struct LocalizeSystem {
+    language: String,
+}
+
+impl<'s> System<'s> for LocalizeSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Localized>,
+        Read<'s, Locale>,
+    );
+
+    fn run(&mut self, (entities, mut ui_texts, mut localized, locale): Self::SystemData) {
+        for (entity, ui_text, _) in (&entities, &mut ui_texts, !&localized.mask().clone()).join() {
+            if ui_text.text.starts_with(KEY_PREFIX) {
+                let key = ui_text.text[KEY_PREFIX.len_utf8()..].to_string();
+                ui_text.text = locale.tr(&key);
+                localized.insert(entity, Localized { key: key }).unwrap();
+            }
+        }
+
+        if self.language != locale.language {
+            self.language = locale.language.clone();
+            for (ui_text, localized) in (&mut ui_texts, &localized).join() {
+                ui_text.text = locale.tr(&localized.key);
+            }
+        }
+    }
+}
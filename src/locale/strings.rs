@@ -0,0 +1,159 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+// Plain text, or text for each plural category ("one", "few", "many", "other")
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Entry {
+    Text(String),
+    Plural(HashMap<String, String>),
+}
+
+// One language, loaded from assets/locale/<language>.json.
+// Kept free of engine types, the lib exports it for `locale_check`
+#[derive(Deserialize, Default)]
+pub struct StringTable {
+    // Shown in the settings screen
+    pub name: String,
+    pub strings: HashMap<String, Entry>,
+}
+
+impl StringTable {
+    pub fn load(path: &str) -> Result<StringTable, String> {
+        let data = fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+        serde_json::from_str::<StringTable>(&data).map_err(|e| format!("Failed to parse {}: {}", path, e))
+    }
+}
+
+// Plural category of a count, rules of the CLDR for a few language families
+pub fn plural_category(language: &str, count: i64) -> &'static str {
+    let n = count.abs();
+    match language {
+        "fr" | "pt" => if n <= 1 { "one" } else { "other" },
+        "ru" | "uk" => {
+            if n % 10 == 1 && n % 100 != 11 {
+                "one"
+            } else if (2..=4).contains(&(n % 10)) && !(12..=14).contains(&(n % 100)) {
+                "few"
+            } else {
+                "many"
+            }
+        },
+        "pl" => {
+            if n == 1 {
+                "one"
+            } else if (2..=4).contains(&(n % 10)) && !(12..=14).contains(&(n % 100)) {
+                "few"
+            } else {
+                "many"
+            }
+        },
+        "ja" | "ko" | "zh" => "other",
+        _ => if n == 1 { "one" } else { "other" },
+    }
+}
+
+// Replaces `{name}` with the argument of that name, unknown names are kept
+pub fn format(template: &str, args: &[(&str, String)]) -> String {
+    let mut text = template.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+// Keys of `reference` the table doesn't have, and plural keys given as plain text or the other way round
+pub fn missing_keys(reference: &StringTable, table: &StringTable) -> Vec<String> {
+    let mut missing = reference.strings.iter()
+        .filter(|(key, entry)| matches!(
+            (entry, table.strings.get(*key)),
+            (_, None) | (Entry::Text(_), Some(Entry::Plural(_))) | (Entry::Plural(_), Some(Entry::Text(_)))
+        ))
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<_>>();
+    missing.sort();
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(entries: &[(&str, Entry)]) -> StringTable {
+        StringTable {
+            name: "test".to_string(),
+            strings: entries.iter().map(|(key, entry)| (key.to_string(), entry.clone())).collect(),
+        }
+    }
+
+    fn text(value: &str) -> Entry {
+        Entry::Text(value.to_string())
+    }
+
+    fn plural() -> Entry {
+        Entry::Plural([("one", "{count} life"), ("other", "{count} lives")].iter()
+            .map(|(category, value)| (category.to_string(), value.to_string()))
+            .collect())
+    }
+
+    #[test]
+    fn english_has_one_and_other() {
+        assert_eq!(plural_category("en", 1), "one");
+        assert_eq!(plural_category("en", 0), "other");
+        assert_eq!(plural_category("en", 21), "other");
+        assert_eq!(plural_category("en", -1), "one");
+    }
+
+    #[test]
+    fn russian_uses_few_and_many() {
+        let cases = [
+            (1, "one"), (21, "one"), (101, "one"),
+            (2, "few"), (4, "few"), (22, "few"), (104, "few"),
+            (0, "many"), (5, "many"), (11, "many"), (12, "many"), (14, "many"), (111, "many"), (25, "many"),
+        ];
+        for (count, category) in cases.iter() {
+            assert_eq!(plural_category("ru", *count), *category, "ru {}", count);
+            assert_eq!(plural_category("uk", *count), *category, "uk {}", count);
+        }
+    }
+
+    #[test]
+    fn polish_one_is_only_one() {
+        let cases = [
+            (1, "one"), (2, "few"), (3, "few"), (22, "few"), (24, "few"),
+            (0, "many"), (5, "many"), (12, "many"), (14, "many"), (21, "many"), (101, "many"),
+        ];
+        for (count, category) in cases.iter() {
+            assert_eq!(plural_category("pl", *count), *category, "pl {}", count);
+        }
+    }
+
+    #[test]
+    fn french_counts_zero_as_one() {
+        assert_eq!(plural_category("fr", 0), "one");
+        assert_eq!(plural_category("fr", 1), "one");
+        assert_eq!(plural_category("fr", 2), "other");
+        assert_eq!(plural_category("ja", 1), "other");
+    }
+
+    #[test]
+    fn missing_keys_are_sorted() {
+        let reference = table(&[("b", text("B")), ("a", text("A")), ("c", text("C"))]);
+        let table = table(&[("c", text("C"))]);
+        assert_eq!(missing_keys(&reference, &table), vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn plural_and_plain_entries_do_not_match() {
+        let reference = table(&[("hud.lives", plural()), ("hud.title", text("Title"))]);
+        let table = table(&[("hud.lives", text("lives")), ("hud.title", plural()), ("extra", text("Extra"))]);
+        assert_eq!(missing_keys(&reference, &table), vec!["hud.lives".to_string(), "hud.title".to_string()]);
+        assert!(missing_keys(&reference, &reference).is_empty());
+    }
+
+    #[test]
+    fn format_replaces_known_names() {
+        assert_eq!(format("{count} of {total}", &[("count", "2".to_string())]), "2 of {total}");
+    }
+}
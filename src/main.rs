@@ -1,31 +1,76 @@
 mod utils;
+mod audio;
 mod camera;
+mod camera_director;
+mod display;
+mod game_speed;
+mod locale;
 mod map;
+mod mods;
 mod unit;
+mod tower;
+mod effect;
+mod endless;
+mod damage;
+mod difficulty;
+mod economy;
+mod ability;
+mod animation;
+mod particle;
+mod script;
+mod selection;
+mod settings;
+mod spatial;
 mod states;
+mod stats;
 mod ui;
 
 use amethyst::{
     prelude::*,
     core::transform::TransformBundle,
     renderer::{
-        plugins::{RenderFlat2D, RenderToWindow},
+        plugins::{RenderDebugLines, RenderFlat2D, RenderToWindow},
         types::DefaultBackend,
         RenderingBundle,
     },
-    input::{is_close_requested, is_key_down, InputBundle, StringBindings, InputEvent, Button},
+    input::{is_close_requested, InputBundle, StringBindings, InputEvent},
     utils::application_root_dir,
     assets::ProgressCounter,
-    ui::{RenderUi, UiBundle, UiCreator, UiEventType, UiFinder, UiText},
-    winit::VirtualKeyCode,
+    ui::{RenderUi, UiBundle, UiCreator, UiEventType, UiFinder},
 };
 use amethyst_tiles::{MortonEncoder2D, RenderTiles2D};
-use utils::{load_sprite_sheet, load_json_data};
-use camera::{initialise_camera, CameraSystem, MouseRaycastSystem};
+use utils::{load_sprite_sheet, load_json_data, get_world_spawn_points, tile_points_to_world};
+use audio::{initialise_audio, AudioInfo, AudioSystem};
+use camera::{initialise_camera, CameraSystem, CameraResizeSystem, CursorPosition, MouseRaycastSystem};
+use display::WindowModeSystem;
+use game_speed::{GameSpeed, GameSpeedSystem};
+use economy::{initialise_economy, EconomyHudSystem};
+use difficulty::{initialise_difficulty, select_next_difficulty, DifficultyPresets};
+use endless::{initialise_endless, toggle_endless, EndlessConfig};
+use camera_director::{CameraDirector, CameraDirectorSystem, CameraMove, CameraTarget};
 use map::{initialise_map, BlockTile, LevelInfo, MapData};
-use unit::{load_unit_info, UnitTyes};
+use mods::{initialise_mods, load_with_mods, Mods};
+use unit::{check_sprite_names, load_unit_info, SpriteSheets, UnitTyes, UnitMoveSystem, UnitDeathSystem};
+use ability::AbilitySystem;
+use animation::{AnimationSystem, UnitAnimationSystem};
+use particle::{load_particle_info, EmitterTypes, ParticleSystem};
+use tower::{load_tower_info, TowerTypes, TowerPlacementSystem, TowerSystem};
+use effect::{load_effect_info, EffectTypes, StatusEffectSystem, EffectIconSystem};
 use states::play::PlayState;
+use states::bindings::BindingsState;
+use states::settings::SettingsState;
+use settings::initialise_settings;
+use stats::{load_save, SaveData};
+use script::{initialise_script, ScriptSystem};
+use locale::{initialise_locale, LocalizeSystem};
 use crate::ui::menu::create_menu;
+use crate::ui::tower_menu::{create_tower_menu, TowerMenuSystem};
+use crate::ui::minimap::{create_minimap, MinimapConfig, MinimapSystem};
+use crate::ui::speed_menu::{create_speed_menu, SpeedMenuSystem};
+use crate::ui::info_panel::{create_info_panel, InfoPanelSystem};
+use crate::ui::health_bar::HealthBarSystem;
+use selection::{Selection, SelectionSystem};
+use spatial::SpatialIndexSystem;
 // use log::info;
 
 #[derive(Default)]
@@ -33,15 +78,23 @@ pub struct GameState {
     pub progress_counter: Option<ProgressCounter>,
 }
 
+// Set from the level info by `initialise_economy`, change money through `economy::credit` and `economy::debit`
 pub struct UserData {
     pub money: f32,
+    pub lives: u32,
 }
 
 impl SimpleState for GameState {
     fn on_start(&mut self, _data: StateData<'_, GameData<'_, '_>>) {
         let world = _data.world;
 
-        world.insert::<UserData>(UserData{ money: 0.0 });
+        world.insert::<CursorPosition>(CursorPosition::default());
+        world.insert::<CameraDirector>(CameraDirector::default());
+        world.insert::<GameSpeed>(GameSpeed::default());
+        world.insert::<Selection>(Selection::default());
+
+        // Before anything is loaded, mods replace asset files
+        initialise_mods(world);
 
         self.progress_counter = Some(Default::default());
 
@@ -51,19 +104,72 @@ impl SimpleState for GameState {
             );
         });
         create_menu(world);
-        
+        create_tower_menu(world);
+        create_speed_menu(world);
+        create_info_panel(world);
+
+        initialise_settings(world);
+        initialise_locale(world);
         initialise_camera(world);
 
         let batch_1_sprite_sheet_handle = load_sprite_sheet(
             world, "images/hyptosis_tile-art-batch-1.png", "images/hyptosis_tile-art-batch-1.ron"
         );
 
-        world.insert::<UnitTyes>(load_json_data::<UnitTyes>("assets/units/info.json"));
+        let sprite_sheets = load_with_mods::<SpriteSheets>(&world.fetch::<Mods>(), "images/sprite_sheets.json");
+        world.insert::<SpriteSheets>(sprite_sheets);
+        let unit_types = load_with_mods::<UnitTyes>(&world.fetch::<Mods>(), "units/info.json");
+        world.insert::<UnitTyes>(unit_types);
         load_unit_info(world);
 
-        world.insert::<MapData>(load_json_data::<MapData>("assets/levels/1_40_40.json"));
-        world.insert::<LevelInfo>(load_json_data::<LevelInfo>("assets/levels/1_info.json"));
+        let path = world.fetch::<Mods>().resolve("effects/info.json");
+        world.insert::<EffectTypes>(load_json_data::<EffectTypes>(&path));
+        load_effect_info(world);
+
+        let path = world.fetch::<Mods>().resolve("particles/info.json");
+        world.insert::<EmitterTypes>(load_json_data::<EmitterTypes>(&path));
+        load_particle_info(world);
+
+        let tower_types = load_with_mods::<TowerTypes>(&world.fetch::<Mods>(), "towers/info.json");
+        world.insert::<TowerTypes>(tower_types);
+        load_tower_info(world);
+        check_sprite_names(world);
+
+        let (level, map_path, info_path) = {
+            let mods = world.fetch::<Mods>();
+            let level = mods.level().unwrap_or_else(|| "1".to_string());
+            let map_path = match mods.resolve_map(&level) {
+                Some(e) => e,
+                None => {
+                    println!("No map found for level {}", level);
+                    std::process::exit(1);
+                }
+            };
+            let info_path = mods.resolve(&format!("levels/{}_info.json", level));
+            (level, map_path, info_path)
+        };
+        world.insert::<MapData>(load_json_data::<MapData>(&map_path));
+        let mut level_info = load_json_data::<LevelInfo>(&info_path);
+        level_info.name = level;
+        world.insert::<LevelInfo>(level_info);
+        world.insert::<SaveData>(load_save());
+        let path = world.fetch::<Mods>().resolve("difficulty.json");
+        world.insert::<DifficultyPresets>(load_json_data::<DifficultyPresets>(&path));
+        initialise_difficulty(world);
+        initialise_economy(world);
+        let path = world.fetch::<Mods>().resolve("endless.json");
+        world.insert::<EndlessConfig>(load_json_data::<EndlessConfig>(&path));
+        initialise_endless(world);
+        let path = world.fetch::<Mods>().resolve("audio/info.json");
+        world.insert::<AudioInfo>(load_json_data::<AudioInfo>(&path));
+        initialise_audio(world);
+        initialise_script(world);
         initialise_map(world, batch_1_sprite_sheet_handle);
+
+        let path = world.fetch::<Mods>().resolve("ui/minimap.json");
+        world.insert::<MinimapConfig>(load_json_data::<MinimapConfig>(&path));
+        create_minimap(world);
+        intro_flyover(world);
     }
 
     fn handle_event(
@@ -71,10 +177,34 @@ impl SimpleState for GameState {
         data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent,
     ) -> SimpleTrans {
-        if let StateEvent::Window(event) = &event {
-            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
-                return Trans::Quit;
-            }
+        match &event {
+            StateEvent::Window(event) => {
+                if is_close_requested(&event) {
+                    return Trans::Quit;
+                }
+            },
+            StateEvent::Input(InputEvent::ActionPressed(action)) => match action.as_str() {
+                "quit" => return Trans::Quit,
+                "bindings_menu" => return Trans::Push(Box::new(BindingsState::default())),
+                "settings_menu" => return Trans::Push(Box::new(SettingsState::default())),
+                // Difficulty is chosen before the level starts
+                "difficulty" => select_next_difficulty(data.world),
+                "endless" => toggle_endless(data.world),
+                _ => (),
+            },
+            StateEvent::Ui(ui_event) if ui_event.event_type == UiEventType::Click => {
+                let (difficulty, endless, settings) = data.world.exec(|ui_finder: UiFinder<'_>| {
+                    (ui_finder.find("difficulty"), ui_finder.find("endless"), ui_finder.find("settings"))
+                });
+                if difficulty == Some(ui_event.target) {
+                    select_next_difficulty(data.world);
+                } else if endless == Some(ui_event.target) {
+                    toggle_endless(data.world);
+                } else if settings == Some(ui_event.target) {
+                    return Trans::Push(Box::new(SettingsState::default()));
+                }
+            },
+            _ => (),
         }
 
         let state_change = match &event {
@@ -84,24 +214,59 @@ impl SimpleState for GameState {
             },
             StateEvent::Input(input) => {
                 match input {
-                    InputEvent::ButtonPressed(dir) => dir == &Button::Key(VirtualKeyCode::Space),
+                    InputEvent::ActionPressed(action) => action == "start_wave",
                     _ => false
                 }
             },
             _ => false
         };
         if state_change {
-            let entity = data.world.exec(|ui_finder: UiFinder<'_>| { ui_finder.find("top-center") });
-            let mut ui_text = data.world.write_storage::<UiText>();
-            let mut top_center_text = ui_text.get_mut(entity.unwrap()).unwrap();
-            top_center_text.text = String::from("level 1");
-
             return Trans::Push(Box::new(PlayState::new(0)));
         }
         return Trans::None;
     }
 }
 
+// Plays the intro moves of the level, or shows every spawn point and the goal
+fn intro_flyover(world: &mut World) {
+    let intro = world.fetch::<LevelInfo>().intro.clone();
+    if !intro.is_empty() {
+        let moves = intro.into_iter().map(|x| {
+            let (target_x, target_y) = match &x.tile {
+                Some(tile) => {
+                    let point = tile_points_to_world(world, &vec![tile.clone()])[0];
+                    (point.x, point.y)
+                },
+                None => (0.0, 0.0),
+            };
+            CameraMove {
+                target: CameraTarget::Point(target_x, target_y),
+                scale: x.scale,
+                duration: x.duration,
+                hold: x.hold,
+                easing: x.easing,
+            }
+        }).collect::<Vec<_>>();
+
+        let mut director = world.write_resource::<CameraDirector>();
+        for camera_move in moves {
+            director.push(camera_move);
+        }
+        return;
+    }
+
+    let spawn_points = get_world_spawn_points(world);
+    let enemy_goal = world.fetch::<LevelInfo>().enemy_goal.clone();
+    let goal = tile_points_to_world(world, &vec![enemy_goal])[0];
+
+    let mut director = world.write_resource::<CameraDirector>();
+    for point in spawn_points {
+        director.move_to(point.x, point.y, Some(1.5), 1.2, 0.6);
+    }
+    director.move_to(goal.x, goal.y, Some(1.5), 1.5, 0.8);
+    director.move_to(0.0, 0.0, Some(2.0), 1.2, 0.0);
+}
+
 fn main() -> amethyst::Result<()> {
     amethyst::start_logger(Default::default());
 
@@ -122,6 +287,7 @@ fn main() -> amethyst::Result<()> {
                         .with_clear([0.05, 0.05, 0.05, 1.0]),
                 )
                 .with_plugin(RenderFlat2D::default())
+                .with_plugin(RenderDebugLines::default())
                 .with_plugin(RenderUi::default())
                 .with_plugin(RenderTiles2D::<BlockTile, MortonEncoder2D>::default()),
         )?
@@ -130,8 +296,33 @@ fn main() -> amethyst::Result<()> {
         .with_bundle(input_bundle)?
         .with_bundle(UiBundle::<StringBindings>::new())?
 
-        .with(CameraSystem, "camera_system", &["input_system"])
-        .with(MouseRaycastSystem, "mouse_raycast_system", &["input_system"])
+        .with(WindowModeSystem::default(), "window_mode_system", &["input_system"])
+        .with(SpeedMenuSystem::default(), "speed_menu_system", &[])
+        .with(GameSpeedSystem::default(), "game_speed_system", &["input_system", "speed_menu_system"])
+        .with(CameraResizeSystem::default(), "camera_resize_system", &[])
+        .with(CameraSystem::default(), "camera_system", &["input_system", "camera_resize_system"])
+        .with(CameraDirectorSystem, "camera_director_system", &["camera_system"])
+        .with(UnitMoveSystem, "unit_move_system", &[])
+        .with(MinimapSystem::default(), "minimap_system", &["camera_director_system", "unit_move_system"])
+        .with(SpatialIndexSystem::default(), "spatial_index_system", &["unit_move_system"])
+        .with(MouseRaycastSystem, "mouse_raycast_system", &["input_system", "spatial_index_system"])
+        .with(TowerPlacementSystem::default(), "tower_placement_system", &["mouse_raycast_system"])
+        .with(TowerSystem, "tower_system", &["spatial_index_system"])
+        .with(TowerMenuSystem::default(), "tower_menu_system", &["tower_placement_system"])
+        .with(SelectionSystem::default(), "selection_system", &["tower_menu_system"])
+        .with(HealthBarSystem, "health_bar_system", &["unit_move_system"])
+        .with(AbilitySystem, "ability_system", &["unit_move_system"])
+        .with(StatusEffectSystem, "status_effect_system", &["tower_system", "ability_system"])
+        .with(InfoPanelSystem, "info_panel_system", &["selection_system", "status_effect_system"])
+        .with(UnitDeathSystem, "unit_death_system", &["status_effect_system"])
+        .with(ScriptSystem::default(), "script_system", &["unit_death_system"])
+        .with(EconomyHudSystem, "economy_hud_system", &["tower_menu_system", "unit_death_system", "script_system"])
+        .with(UnitAnimationSystem, "unit_animation_system", &["unit_move_system"])
+        .with(AnimationSystem, "animation_system", &["unit_animation_system", "ability_system", "tower_menu_system"])
+        .with(ParticleSystem::default(), "particle_system", &["unit_death_system", "tower_menu_system"])
+        .with(EffectIconSystem, "effect_icon_system", &["status_effect_system"])
+        .with(AudioSystem::default(), "audio_system", &["tower_system", "unit_death_system"])
+        .with(LocalizeSystem::default(), "localize_system", &[])
         ;
 
     let mut game = Application::new(assets_dir, GameState::default(), game_data)?;
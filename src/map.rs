@@ -14,6 +14,8 @@ use serde::{Deserialize, Serialize};
 use amethyst_rendy::palette::Srgba;
 use std::collections::HashMap;
 use std::iter::FromIterator;
+use crate::audio::MusicTrack;
+use crate::camera_director::IntroMove;
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct LayerData {
@@ -33,14 +35,89 @@ pub struct MapData {
     pub tilewidth: u32,
 }
 
+fn default_starting_money() -> f32 {
+    200.0
+}
+
+fn default_lives() -> u32 {
+    20
+}
+
+fn default_wave_delay() -> u32 {
+    5000
+}
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct LevelInfo {
+    // Level the info was loaded for, set by the loader
+    #[serde(skip)]
+    pub name: String,
     pub enemy_spawn: Vec<Vec<u32>>,
+    // Ground waypoints for every spawn point, in the same order as `enemy_spawn`
+    pub enemy_paths: Vec<Vec<Vec<u32>>>,
+    // Optional waypoints for flying units, they fly straight to the goal otherwise
+    #[serde(default)]
+    pub flying_path: Vec<Vec<u32>>,
+    pub enemy_goal: Vec<u32>,
     pub spawn_speed: u32,
+    // Milliseconds between the end of a wave and the first spawn of the next one
+    #[serde(default = "default_wave_delay")]
+    pub wave_delay: u32,
     pub levels: Vec<HashMap<String, u32>>,
+    #[serde(default = "default_starting_money")]
+    pub starting_money: f32,
+    #[serde(default = "default_lives")]
+    pub lives: u32,
+    // Paid when a wave is cleared
+    #[serde(default)]
+    pub wave_income: f32,
+    // Part of the banked money paid when a wave is cleared, 0 to disable
+    #[serde(default)]
+    pub interest: f32,
+    #[serde(default)]
+    pub interest_cap: Option<f32>,
+    // Tiles towers can be built on, any tile when empty
+    #[serde(default)]
+    pub buildable: Vec<Vec<u32>>,
+    // Played in a loop while the level is loaded
+    #[serde(default)]
+    pub music: Vec<MusicTrack>,
+    // Camera moves before the level starts, every spawn point and the goal are shown when empty
+    #[serde(default)]
+    pub intro: Vec<IntroMove>,
+    // Rhai script of level events, a path under assets/ like "levels/1.rhai"
+    #[serde(default)]
+    pub script: Option<String>,
+}
+
+// Tiles on a straight line between two tiles, both ends included
+fn line_tiles(from: &[u32], to: &[u32]) -> Vec<(i64, i64)> {
+    let (x0, y0) = (from[0] as i64, from[1] as i64);
+    let (dx, dy) = (to[0] as i64 - x0, to[1] as i64 - y0);
+    let steps = dx.abs().max(dy.abs());
+    if steps == 0 {
+        return vec![(x0, y0)];
+    }
+    (0..=steps)
+        .map(|i| {
+            let t = i as f64 / steps as f64;
+            (x0 + (dx as f64 * t).round() as i64, y0 + (dy as f64 * t).round() as i64)
+        })
+        .collect()
 }
 
 impl LevelInfo {
+    // Ground units walk from their spawn through its waypoints to the goal in straight lines
+    pub fn is_on_path(&self, x: u32, y: u32) -> bool {
+        let tile = (x as i64, y as i64);
+        self.enemy_spawn.iter().zip(self.enemy_paths.iter()).any(|(spawn, path)| {
+            let mut points = vec![spawn];
+            points.extend(path.iter());
+            points.push(&self.enemy_goal);
+            points.windows(2).any(|pair| line_tiles(pair[0], pair[1]).contains(&tile))
+        })
+    }
+
     pub fn get_units_count(&self, wave: u32) -> u32 {
         let mut count: u32 = 0;
         for (_key, value) in &self.levels[wave as usize] {
@@ -49,22 +126,23 @@ impl LevelInfo {
         count
     }
 
+    // Units of a wave are mixed: one of each type in name order until every count is spawned
     pub fn get_unit_by_index(&self, wave: u32, index: u32) -> Option<String> {
-        let units_count = self.get_units_count(wave);
-
-        let mut wave_units = self.levels[wave as usize].clone();
-        let units_types = Vec::from_iter(self.levels[wave as usize].keys());
-
-        for i in 0..units_count {
-            let key = units_types[(i % units_types.len() as u32) as usize];
-            if i == index {
-                return Some(key.to_string());
-            }
-
-            *wave_units.get_mut(key).unwrap() += 1;
-
-            if wave_units[key] <= 0 {
-                wave_units.remove(key);
+        let mut wave_units = Vec::from_iter(self.levels.get(wave as usize)?.iter());
+        wave_units.sort();
+        let mut left = wave_units.iter().map(|(_, count)| **count).collect::<Vec<_>>();
+
+        let mut i = 0;
+        while left.iter().any(|x| *x > 0) {
+            for (j, (name, _)) in wave_units.iter().enumerate() {
+                if left[j] == 0 {
+                    continue;
+                }
+                if i == index {
+                    return Some(name.to_string());
+                }
+                left[j] -= 1;
+                i += 1;
             }
         }
         None
@@ -0,0 +1,364 @@
+// Procedural level generator, writes Tiled JSON maps and level info.
+// Kept free of engine types, exported by the library target for the `mapgen` binary
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use crate::random::SeededRandom;
+
+// Hyptosis tile ids as they are stored in the Tiled json, 0 is empty
+pub const GRASS: u32 = 855;
+pub const PATH: u32 = 853;
+pub const STONE: u32 = 601;
+// Small single tile details drawn over grass
+pub const GROUND_DECORATIONS: [u32; 8] = [728, 697, 757, 821, 644, 674, 735, 879];
+pub const PROP_DECORATIONS: [u32; 3] = [230, 260, 506];
+// Level info fields taken from the template, the rest depends on the map
+pub const TEMPLATE_FIELDS: [&str; 9] = [
+    "spawn_speed", "wave_delay", "levels", "starting_money", "lives", "wave_income", "interest", "interest_cap", "music",
+];
+
+pub struct MapParams {
+    pub seed: u64,
+    pub width: u32,
+    pub height: u32,
+    pub spawns: u32,
+    // Chance of a decoration on a free tile
+    pub decoration_density: f32,
+}
+
+impl Default for MapParams {
+    fn default() -> Self {
+        MapParams {
+            seed: 0,
+            width: 40,
+            height: 40,
+            spawns: 3,
+            decoration_density: 0.08,
+        }
+    }
+}
+
+pub struct GeneratedLevel {
+    pub width: u32,
+    pub height: u32,
+    // Base, ground decorations, props and an empty layer for hand placed objects, as in the shipped level
+    pub layers: Vec<Vec<u32>>,
+    pub enemy_spawn: Vec<[u32; 2]>,
+    // Corner waypoints of every spawn, the goal is not included
+    pub enemy_paths: Vec<Vec<[u32; 2]>>,
+    pub enemy_goal: [u32; 2],
+    // Tiles next to the paths where towers can be built
+    pub buildable: Vec<[u32; 2]>,
+}
+
+fn index(width: u32, x: u32, y: u32) -> usize {
+    (y * width + x) as usize
+}
+
+// Moves horizontally first, then vertically, recording every tile
+fn step_to(x: &mut u32, y: &mut u32, target_x: u32, target_y: u32, tiles: &mut Vec<[u32; 2]>) {
+    while *x != target_x {
+        if *x > target_x { *x -= 1 } else { *x += 1 }
+        tiles.push([*x, *y]);
+    }
+    while *y != target_y {
+        if *y > target_y { *y -= 1 } else { *y += 1 }
+        tiles.push([*x, *y]);
+    }
+}
+
+// Walks left from the spawn with vertical turns, returns corner waypoints and every visited tile
+fn walk_path(random: &mut SeededRandom, params: &MapParams, spawn: [u32; 2], goal: [u32; 2]) -> (Vec<[u32; 2]>, Vec<[u32; 2]>) {
+    let margin = 3;
+    let approach_x = goal[0] + 2;
+    let (mut x, mut y) = (spawn[0], spawn[1]);
+    let mut corners = Vec::new();
+    let mut tiles = vec![[x, y]];
+
+    while x > approach_x {
+        let next_x = x.saturating_sub(random.range(4, 9)).max(approach_x);
+        let row = y;
+        step_to(&mut x, &mut y, next_x, row, &mut tiles);
+        corners.push([x, y]);
+        if x == approach_x {
+            break;
+        }
+        // Short turns keep paths of different spawns apart
+        let reach = params.height / 5;
+        let next_y = random.range(y.saturating_sub(reach).max(margin), (y + reach).min(params.height - 1 - margin));
+        let column = x;
+        step_to(&mut x, &mut y, column, next_y, &mut tiles);
+        corners.push([x, y]);
+    }
+
+    // Straight into the goal row
+    step_to(&mut x, &mut y, approach_x, goal[1], &mut tiles);
+    corners.push([x, y]);
+    step_to(&mut x, &mut y, goal[0], goal[1], &mut tiles);
+    corners.dedup();
+    (corners, tiles)
+}
+
+// Path tiles connect the spawn with the goal
+fn is_connected(params: &MapParams, path: &[bool], spawn: [u32; 2], goal: [u32; 2]) -> bool {
+    let mut visited = vec![false; path.len()];
+    let mut queue = VecDeque::new();
+    queue.push_back(spawn);
+    visited[index(params.width, spawn[0], spawn[1])] = true;
+
+    while let Some([x, y]) = queue.pop_front() {
+        if [x, y] == goal {
+            return true;
+        }
+        let neighbours = [
+            (x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1),
+        ];
+        for &(nx, ny) in neighbours.iter() {
+            if nx >= params.width || ny >= params.height {
+                continue;
+            }
+            let i = index(params.width, nx, ny);
+            if path[i] && !visited[i] {
+                visited[i] = true;
+                queue.push_back([nx, ny]);
+            }
+        }
+    }
+    false
+}
+
+pub fn generate(params: &MapParams) -> Result<GeneratedLevel, String> {
+    if params.width < 16 || params.height < 12 {
+        return Err("map must be at least 16x12 tiles".to_string());
+    }
+    if params.spawns == 0 || params.spawns > params.height / 4 {
+        return Err(format!("spawn count must be between 1 and {}", params.height / 4));
+    }
+
+    let mut random = SeededRandom::new(params.seed);
+    let (width, height) = (params.width, params.height);
+    let size = (width * height) as usize;
+    let goal = [2, height / 2];
+
+    // Spawns are spread along the right edge
+    let band = height / params.spawns;
+    let enemy_spawn = (0..params.spawns)
+        .map(|i| [width - 1, (i * band + random.range(band / 4, band * 3 / 4)).max(2).min(height - 3)])
+        .collect::<Vec<_>>();
+
+    let mut path = vec![false; size];
+    let mut enemy_paths = Vec::new();
+    for spawn in enemy_spawn.iter() {
+        let (corners, tiles) = walk_path(&mut random, params, *spawn, goal);
+        for [x, y] in tiles {
+            path[index(width, x, y)] = true;
+        }
+        if !is_connected(params, &path, *spawn, goal) {
+            return Err(format!("path from {:?} doesn't reach the goal", spawn));
+        }
+        enemy_paths.push(corners);
+    }
+
+    let mut base = vec![GRASS; size];
+    // Stone yard around the goal
+    for y in goal[1].saturating_sub(2)..=(goal[1] + 2).min(height - 1) {
+        for x in 0..=goal[0] + 1 {
+            base[index(width, x, y)] = STONE;
+        }
+    }
+    for (i, is_path) in path.iter().enumerate() {
+        if *is_path {
+            base[i] = PATH;
+        }
+    }
+
+    // Towers go up to two tiles from a path
+    let near_path = |x: u32, y: u32| {
+        let (min_x, max_x) = (x.saturating_sub(2), (x + 2).min(width - 1));
+        let (min_y, max_y) = (y.saturating_sub(2), (y + 2).min(height - 1));
+        (min_y..=max_y).any(|ny| (min_x..=max_x).any(|nx| path[index(width, nx, ny)]))
+    };
+    let mut buildable = Vec::new();
+    let mut ground = vec![0; size];
+    let mut props = vec![0; size];
+    for y in 0..height {
+        for x in 0..width {
+            let i = index(width, x, y);
+            if base[i] != GRASS {
+                continue;
+            }
+            if near_path(x, y) {
+                buildable.push([x, y]);
+                continue;
+            }
+            if random.next_f32() < params.decoration_density {
+                ground[i] = GROUND_DECORATIONS[(random.next_u64() % GROUND_DECORATIONS.len() as u64) as usize];
+            } else if random.next_f32() < params.decoration_density * 0.3 {
+                props[i] = PROP_DECORATIONS[(random.next_u64() % PROP_DECORATIONS.len() as u64) as usize];
+            }
+        }
+    }
+
+    Ok(GeneratedLevel {
+        width: width,
+        height: height,
+        layers: vec![base, ground, props, vec![0; size]],
+        enemy_spawn: enemy_spawn,
+        enemy_paths: enemy_paths,
+        enemy_goal: goal,
+        buildable: buildable,
+    })
+}
+
+// Same layout as maps saved by Tiled 1.3, so they open in the editor
+pub fn to_tiled_json(level: &GeneratedLevel, tileset: &str) -> Value {
+    let layers = level.layers.iter().enumerate()
+        .map(|(i, data)| json!({
+            "data": data,
+            "height": level.height,
+            "id": i + 1,
+            "name": format!("Tile Layer {}", i),
+            "opacity": 1,
+            "type": "tilelayer",
+            "visible": true,
+            "width": level.width,
+            "x": 0,
+            "y": 0,
+        }))
+        .collect::<Vec<_>>();
+
+    json!({
+        "compressionlevel": -1,
+        "editorsettings": {"export": {"target": "."}},
+        "height": level.height,
+        "infinite": false,
+        "layers": layers,
+        "nextlayerid": level.layers.len() + 1,
+        "nextobjectid": 1,
+        "orientation": "orthogonal",
+        "renderorder": "right-down",
+        "tiledversion": "1.3.1",
+        "tileheight": 32,
+        "tilesets": [{"firstgid": 1, "source": tileset}],
+        "tilewidth": 32,
+        "type": "map",
+        "version": 1.2,
+        "width": level.width,
+    })
+}
+
+// Level info of the map with the waves and economy of a template level info
+pub fn to_level_info_json(level: &GeneratedLevel, template: &Value) -> Result<Value, String> {
+    let mut info = json!({
+        "enemy_spawn": level.enemy_spawn,
+        "enemy_paths": level.enemy_paths,
+        "flying_path": [],
+        "enemy_goal": level.enemy_goal,
+        "buildable": level.buildable,
+    });
+    for field in TEMPLATE_FIELDS.iter() {
+        if let Some(value) = template.get(*field) {
+            info[*field] = value.clone();
+        }
+    }
+    for field in ["spawn_speed", "levels"].iter() {
+        if info.get(*field).is_none() {
+            return Err(format!("template has no {}", field));
+        }
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [(u32, u32); 4] = [(16, 12), (40, 40), (64, 32), (30, 60)];
+
+    fn levels() -> Vec<GeneratedLevel> {
+        let mut levels = Vec::new();
+        for &(width, height) in SIZES.iter() {
+            for spawns in 1..=height / 4 {
+                for seed in 0..20 {
+                    let params = MapParams {
+                        seed: seed,
+                        width: width,
+                        height: height,
+                        spawns: spawns,
+                        ..Default::default()
+                    };
+                    levels.push(generate(&params).unwrap());
+                }
+            }
+        }
+        levels
+    }
+
+    fn in_bounds(level: &GeneratedLevel, tile: [u32; 2]) -> bool {
+        tile[0] < level.width && tile[1] < level.height
+    }
+
+    #[test]
+    fn paths_reach_the_goal_over_path_tiles() {
+        for level in levels() {
+            let base = &level.layers[0];
+            for (spawn, corners) in level.enemy_spawn.iter().zip(level.enemy_paths.iter()) {
+                let (mut x, mut y) = (spawn[0], spawn[1]);
+                let mut tiles = vec![[x, y]];
+                for corner in corners.iter().chain(std::iter::once(&level.enemy_goal)) {
+                    step_to(&mut x, &mut y, corner[0], corner[1], &mut tiles);
+                }
+                assert_eq!([x, y], level.enemy_goal);
+                for tile in tiles {
+                    assert_eq!(base[index(level.width, tile[0], tile[1])], PATH, "{:?} is not a path tile", tile);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spawns_paths_and_buildable_tiles_are_in_bounds() {
+        for level in levels() {
+            assert!(in_bounds(&level, level.enemy_goal));
+            assert!(level.enemy_spawn.iter().all(|x| in_bounds(&level, *x)));
+            assert!(level.enemy_paths.iter().flatten().all(|x| in_bounds(&level, *x)));
+            assert!(level.buildable.iter().all(|x| in_bounds(&level, *x)));
+            assert!(level.layers.iter().all(|x| x.len() == (level.width * level.height) as usize));
+        }
+    }
+
+    #[test]
+    fn towers_are_not_built_on_paths() {
+        for level in levels() {
+            let base = &level.layers[0];
+            assert!(level.buildable.iter().all(|x| base[index(level.width, x[0], x[1])] == GRASS));
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_level() {
+        for &(width, height) in SIZES.iter() {
+            for seed in 0..10 {
+                let params = MapParams {
+                    seed: seed,
+                    width: width,
+                    height: height,
+                    ..Default::default()
+                };
+                let (a, b) = (generate(&params).unwrap(), generate(&params).unwrap());
+                assert_eq!(a.layers, b.layers);
+                assert_eq!(a.enemy_spawn, b.enemy_spawn);
+                assert_eq!(a.enemy_paths, b.enemy_paths);
+                assert_eq!(a.buildable, b.buildable);
+            }
+        }
+    }
+
+    #[test]
+    fn too_small_maps_are_rejected() {
+        let params = MapParams {
+            width: 10,
+            ..Default::default()
+        };
+        assert!(generate(&params).is_err());
+    }
+}
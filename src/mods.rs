@@ -0,0 +1,295 @@
+use amethyst::{
+    assets::{Directory, Loader, Source},
+    prelude::*,
+    utils::application_root_dir,
+    Error,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::tower::{TowerType, TowerTypes};
+use crate::unit::{SpriteSheetInfo, SpriteSheets, UnitTyes, UnitType};
+use crate::utils::load_json_data;
+
+pub const MODS_DIR: &str = "mods";
+// Enabled mods in load order, every mod directory is loaded in name order without it
+pub const LOAD_ORDER_FILE: &str = "mods/load_order.json";
+pub const MANIFEST_FILE: &str = "mod.json";
+// Merged by type name instead of replacing the whole file
+const MERGED_FILES: [&str; 3] = ["units/info.json", "towers/info.json", "images/sprite_sheets.json"];
+
+// mods/<dir>/mod.json. Other files of the mod directory take the place of files
+// with the same path under assets/, later mods in the load order win
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModManifest {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    // Level loaded at start instead of level 1
+    #[serde(default)]
+    pub level: Option<String>,
+}
+
+pub struct LoadedMod {
+    pub manifest: ModManifest,
+    // mods/<dir>
+    pub dir: PathBuf,
+}
+
+// Mods in load order
+#[derive(Default)]
+pub struct Mods {
+    pub mods: Vec<LoadedMod>,
+}
+
+fn load_manifest(dir: &Path) -> Option<ModManifest> {
+    let path = dir.join(MANIFEST_FILE);
+    let data = match fs::read_to_string(&path) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Skipping mod {}: can't read {}: {}", dir.display(), path.display(), e);
+            return None;
+        }
+    };
+    match serde_json::from_str::<ModManifest>(&data) {
+        Ok(e) => Some(e),
+        Err(e) => {
+            println!("Skipping mod {}: failed to parse {}: {}", dir.display(), path.display(), e);
+            None
+        }
+    }
+}
+
+// Files of a directory relative to it, with `/` separators
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            list_files(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let parts = relative.components().map(|x| x.as_os_str().to_string_lossy()).collect::<Vec<_>>();
+            files.push(parts.join("/"));
+        }
+    }
+}
+
+pub fn load_mods() -> Mods {
+    let dirs = if Path::new(LOAD_ORDER_FILE).exists() {
+        load_json_data::<Vec<String>>(LOAD_ORDER_FILE)
+    } else {
+        let mut dirs = fs::read_dir(MODS_DIR)
+            .map(|dir| {
+                dir.filter_map(|e| e.ok())
+                    .filter(|e| e.path().is_dir())
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        dirs.sort();
+        dirs
+    };
+
+    let mut mods = Mods::default();
+    for dir in dirs {
+        let dir = Path::new(MODS_DIR).join(dir);
+        if let Some(manifest) = load_manifest(&dir) {
+            println!("Loading mod {} {} from {}", manifest.name, manifest.version, dir.display());
+            mods.mods.push(LoadedMod { manifest: manifest, dir: dir });
+        }
+    }
+    mods.report_conflicts();
+    mods
+}
+
+impl Mods {
+    // Path to read a file of assets/ from, taken from the last mod that has it
+    pub fn resolve(&self, path: &str) -> String {
+        self.mods.iter().rev()
+            .map(|x| x.dir.join(path))
+            .find(|x| x.exists())
+            .map_or(format!("assets/{}", path), |x| x.to_string_lossy().to_string())
+    }
+
+    // Mod name and path for every mod having the file, in load order
+    pub fn overlays(&self, path: &str) -> Vec<(String, String)> {
+        self.mods.iter()
+            .map(|x| (x.manifest.name.clone(), x.dir.join(path)))
+            .filter(|(_, x)| x.exists())
+            .map(|(name, x)| (name, x.to_string_lossy().to_string()))
+            .collect()
+    }
+
+    // Level name from the last mod which sets one
+    pub fn level(&self) -> Option<String> {
+        self.mods.iter().rev().find_map(|x| x.manifest.level.clone())
+    }
+
+    // Map of a level is `levels/<level>_<width>_<height>.json`
+    pub fn resolve_map(&self, level: &str) -> Option<String> {
+        let prefix = format!("{}_", level);
+        let info = format!("{}_info.json", level);
+        let dirs = self.mods.iter().rev()
+            .map(|x| x.dir.join("levels"))
+            .chain(Some(Path::new("assets").join("levels")));
+        for dir in dirs {
+            let mut files = Vec::new();
+            list_files(&dir, &dir, &mut files);
+            files.sort();
+            if let Some(file) = files.iter().find(|x| x.starts_with(&prefix) && x.ends_with(".json") && **x != info) {
+                return Some(dir.join(file).to_string_lossy().to_string());
+            }
+        }
+        None
+    }
+
+    // Files given by several mods, only the last one is used
+    fn report_conflicts(&self) {
+        let mut owners = HashMap::<String, String>::new();
+        for loaded in self.mods.iter() {
+            let mut files = Vec::new();
+            list_files(&loaded.dir, &loaded.dir, &mut files);
+            for file in files {
+                if file == MANIFEST_FILE || MERGED_FILES.contains(&file.as_str()) {
+                    continue;
+                }
+                if let Some(owner) = owners.insert(file.clone(), loaded.manifest.name.clone()) {
+                    println!("Mod conflict: {} of {} is replaced by {}", file, owner, loaded.manifest.name);
+                }
+            }
+        }
+
+        let levels = self.mods.iter().filter(|x| x.manifest.level.is_some()).collect::<Vec<_>>();
+        if levels.len() > 1 {
+            let names = levels.iter().map(|x| x.manifest.name.as_str()).collect::<Vec<_>>();
+            println!("Mod conflict: {} all set the level, {} is used", names.join(", "), names[names.len() - 1]);
+        }
+    }
+}
+
+// Reads asset files from the mod directories first, then from assets/
+pub struct OverlaySource {
+    // Last mod first, assets/ at the end
+    dirs: Vec<(PathBuf, Directory)>,
+}
+
+impl Source for OverlaySource {
+    fn modified(&self, path: &str) -> Result<u64, Error> {
+        self.find(path).modified(path)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        self.find(path).load(path)
+    }
+}
+
+impl OverlaySource {
+    fn find(&self, path: &str) -> &Directory {
+        self.dirs.iter()
+            .find(|(root, _)| root.join(path).exists())
+            .map_or(&self.dirs[self.dirs.len() - 1].1, |(_, directory)| directory)
+    }
+}
+
+// Replaces the default asset source, so images, sprite sheets, fonts and UI prefabs come from mods
+pub fn initialise_mods(world: &mut World) {
+    let mods = load_mods();
+    let root = match application_root_dir() {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't find application directory: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let dirs = mods.mods.iter().rev()
+        .map(|x| root.join(&x.dir))
+        .chain(Some(root.join("assets")))
+        .map(|x| (x.clone(), Directory::new(x)))
+        .collect::<Vec<_>>();
+    world.write_resource::<Loader>().add_source("", OverlaySource { dirs: dirs });
+    world.insert::<Mods>(mods);
+}
+
+// Data files whose entries mods add or replace by name
+pub trait NamedTypes: DeserializeOwned {
+    type Item;
+    fn types(&mut self) -> &mut Vec<Self::Item>;
+    fn into_types(self) -> Vec<Self::Item>;
+    fn name(item: &Self::Item) -> &str;
+}
+
+impl NamedTypes for UnitTyes {
+    type Item = UnitType;
+
+    fn types(&mut self) -> &mut Vec<UnitType> {
+        &mut self.types
+    }
+
+    fn into_types(self) -> Vec<UnitType> {
+        self.types
+    }
+
+    fn name(item: &UnitType) -> &str {
+        &item.name
+    }
+}
+
+impl NamedTypes for SpriteSheets {
+    type Item = SpriteSheetInfo;
+
+    fn types(&mut self) -> &mut Vec<SpriteSheetInfo> {
+        &mut self.sheets
+    }
+
+    fn into_types(self) -> Vec<SpriteSheetInfo> {
+        self.sheets
+    }
+
+    fn name(item: &SpriteSheetInfo) -> &str {
+        &item.name
+    }
+}
+
+impl NamedTypes for TowerTypes {
+    type Item = TowerType;
+
+    fn types(&mut self) -> &mut Vec<TowerType> {
+        &mut self.types
+    }
+
+    fn into_types(self) -> Vec<TowerType> {
+        self.types
+    }
+
+    fn name(item: &TowerType) -> &str {
+        &item.name
+    }
+}
+
+// Base file from assets/ with the types of every mod added, a type of the same name is replaced.
+// Other fields of the file keep the base values
+pub fn load_with_mods<T: NamedTypes>(mods: &Mods, path: &str) -> T {
+    let mut data = load_json_data::<T>(&format!("assets/{}", path));
+    // Mod which gave each type, base types have none
+    let mut owners = HashMap::<String, String>::new();
+
+    for (mod_name, file) in mods.overlays(path) {
+        for item in load_json_data::<T>(&file).into_types() {
+            let name = T::name(&item).to_string();
+            if let Some(owner) = owners.insert(name.clone(), mod_name.clone()) {
+                println!("Mod conflict: {} in {} of {} is replaced by {}", name, path, owner, mod_name);
+            }
+            let types = data.types();
+            match types.iter().position(|x| T::name(x) == name) {
+                Some(i) => types[i] = item,
+                None => types.push(item),
+            }
+        }
+    }
+    data
+}
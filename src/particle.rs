@@ -0,0 +1,269 @@
+use amethyst::{
+    prelude::*,
+    core::{transform::Transform, Hidden, Time},
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Entity, Join, Read, System, SystemData, WriteStorage,
+    },
+    renderer::{resources::Tint, SpriteRender, Transparent},
+    shrev::{EventChannel, ReaderId},
+    core::math::Vector3,
+};
+use amethyst_rendy::palette::Srgba;
+use serde::{Deserialize, Serialize};
+use crate::unit::SpriteData;
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct EmitterType {
+    pub name: String,
+    pub sprite_name: String,
+    pub sprite_id: u32,
+    // Particles created by one emit
+    pub count: u32,
+    // Particles per second while attached to an entity with an `Emitter`
+    #[serde(default)]
+    pub rate: f32,
+    // Seconds
+    pub lifetime: f32,
+    pub speed_min: f32,
+    pub speed_max: f32,
+    // Center and width of the emit cone in radians
+    #[serde(default)]
+    pub direction: f32,
+    pub spread: f32,
+    #[serde(default)]
+    pub gravity: f32,
+    pub colour_start: [f32; 4],
+    pub colour_end: [f32; 4],
+    pub scale_start: f32,
+    pub scale_end: f32,
+}
+
+fn default_max_particles() -> usize {
+    1000
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct EmitterTypes {
+    pub types: Vec<EmitterType>,
+    // Emits are cut once this many particles are alive
+    #[serde(default = "default_max_particles")]
+    pub max_particles: usize,
+}
+
+pub struct ParticleEvent {
+    pub emitter: String,
+    pub pos: Vector3<f32>,
+}
+
+pub struct Particle {
+    pub velocity: (f32, f32),
+    pub gravity: f32,
+    pub age: f32,
+    pub lifetime: f32,
+    pub colour_start: [f32; 4],
+    pub colour_end: [f32; 4],
+    pub scale_start: f32,
+    pub scale_end: f32,
+}
+
+impl Component for Particle {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// Emits particles continuously at the entity position, like a trail behind a moving entity
+pub struct Emitter {
+    pub emitter: String,
+    // Particles owed since the last one was emitted
+    pending: f32,
+}
+
+impl Component for Emitter {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Emitter {
+    pub fn new(emitter: String) -> Emitter {
+        Emitter {
+            emitter: emitter,
+            pending: 0.0,
+        }
+    }
+}
+
+pub fn load_particle_info(world: &mut World) {
+    // Event channel is created by `ParticleSystem` setup
+    world.register::<Particle>();
+    world.register::<Emitter>();
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// Small xorshift generator, particles don't need anything better
+struct Random(u32);
+
+impl Random {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 % 10000) as f32 / 10000.0
+    }
+}
+
+// Moves particles and recycles entities of dead particles
+pub struct ParticleSystem {
+    reader_id: Option<ReaderId<ParticleEvent>>,
+    // Hidden particle entities ready for reuse
+    pool: Vec<Entity>,
+    live: usize,
+    random: Random,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        ParticleSystem {
+            reader_id: None,
+            pool: Vec::new(),
+            live: 0,
+            random: Random(0x9E37_79B9),
+        }
+    }
+}
+
+impl<'s> System<'s> for ParticleSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Particle>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, Tint>,
+        WriteStorage<'s, Hidden>,
+        WriteStorage<'s, Transparent>,
+        WriteStorage<'s, Emitter>,
+        Read<'s, EventChannel<ParticleEvent>>,
+        Read<'s, EmitterTypes>,
+        Read<'s, SpriteData>,
+        Read<'s, Time>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<ParticleEvent>>().register_reader());
+    }
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut particles,
+            mut transforms,
+            mut sprites,
+            mut tints,
+            mut hiddens,
+            mut transparents,
+            mut emitters,
+            events,
+            emitter_types,
+            sprite_data,
+            time,
+        ): Self::SystemData,
+    ) {
+        let delta = time.delta_seconds();
+        let mut dead = Vec::<Entity>::new();
+
+        for (entity, particle, transform, tint, _) in
+            (&entities, &mut particles, &mut transforms, &mut tints, !&hiddens).join()
+        {
+            particle.age += delta;
+            if particle.age >= particle.lifetime {
+                dead.push(entity);
+                continue;
+            }
+
+            particle.velocity.1 -= particle.gravity * delta;
+            transform.prepend_translation_x(particle.velocity.0 * delta);
+            transform.prepend_translation_y(particle.velocity.1 * delta);
+
+            let t = particle.age / particle.lifetime;
+            let scale = lerp(particle.scale_start, particle.scale_end, t);
+            transform.set_scale(Vector3::new(scale, scale, 1.0));
+
+            let c = (0..4).map(|i| lerp(particle.colour_start[i], particle.colour_end[i], t)).collect::<Vec<_>>();
+            tint.0 = Srgba::new(c[0], c[1], c[2], c[3]);
+        }
+
+        for entity in dead {
+            hiddens.insert(entity, Hidden).unwrap();
+            self.pool.push(entity);
+            self.live -= 1;
+        }
+
+        // Emitter name, position and particle count, `count` of the type for events
+        let mut emits = events.read(self.reader_id.as_mut().unwrap())
+            .map(|x| (x.emitter.clone(), x.pos, None))
+            .collect::<Vec<_>>();
+        for (emitter, transform) in (&mut emitters, &transforms).join() {
+            let rate = emitter_types.types.iter().find(|&x| x.name == emitter.emitter).map_or(0.0, |x| x.rate);
+            emitter.pending += rate * delta;
+            let count = emitter.pending.floor();
+            emitter.pending -= count;
+            if count >= 1.0 {
+                emits.push((emitter.emitter.clone(), *transform.translation(), Some(count as u32)));
+            }
+        }
+
+        for (name, pos, count) in emits {
+            let emitter = match emitter_types.types.iter().find(|&x| x.name == name) {
+                Some(e) => e,
+                None => {
+                    println!("Unknown particle emitter {}", name);
+                    continue;
+                }
+            };
+            let sprite_sheet = match sprite_data.sprite_handles.get(&emitter.sprite_name) {
+                Some(e) => e.clone(),
+                None => continue,
+            };
+
+            for _ in 0..count.unwrap_or(emitter.count) {
+                if self.live >= emitter_types.max_particles {
+                    break;
+                }
+                let entity = match self.pool.pop() {
+                    Some(e) => e,
+                    None => entities.create(),
+                };
+                self.live += 1;
+
+                let angle = emitter.direction + (self.random.next() - 0.5) * emitter.spread;
+                let speed = lerp(emitter.speed_min, emitter.speed_max, self.random.next());
+
+                let mut transform = Transform::default();
+                transform.set_translation_xyz(pos.x, pos.y, 0.6);
+                transform.set_scale(Vector3::new(emitter.scale_start, emitter.scale_start, 1.0));
+
+                let c = emitter.colour_start;
+                hiddens.remove(entity);
+                transforms.insert(entity, transform).unwrap();
+                tints.insert(entity, Tint(Srgba::new(c[0], c[1], c[2], c[3]))).unwrap();
+                transparents.insert(entity, Transparent).unwrap();
+                sprites.insert(entity, SpriteRender {
+                    sprite_sheet: sprite_sheet.clone(),
+                    sprite_number: emitter.sprite_id as usize,
+                }).unwrap();
+                particles.insert(entity, Particle {
+                    velocity: (angle.cos() * speed, angle.sin() * speed),
+                    gravity: emitter.gravity,
+                    age: 0.0,
+                    lifetime: emitter.lifetime,
+                    colour_start: emitter.colour_start,
+                    colour_end: emitter.colour_end,
+                    scale_start: emitter.scale_start,
+                    scale_end: emitter.scale_end,
+                }).unwrap();
+            }
+        }
+    }
+}
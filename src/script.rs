@@ -0,0 +1,526 @@
+use amethyst::{
+    prelude::*,
+    core::{math::Point3, Time},
+    ecs::{Join, LazyUpdate, Read, ReadStorage, ReaderId, System, SystemData, Write, WriteExpect},
+    shrev::EventChannel,
+};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, FuncArgs, Scope, AST, FLOAT, INT};
+use std::collections::HashSet;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use crate::economy::{credit, Reason, TransactionLog};
+use crate::locale::{Locale, KEY_PREFIX};
+use crate::map::{LevelInfo, MapData};
+use crate::mods::Mods;
+use crate::tower::PLAYER_TEAM;
+use crate::ui::minimap::refresh_minimap_cell;
+use crate::unit::{spawn_unit_type, Unit, UnitTyes};
+use crate::UserData;
+
+// Limits of one hook call, a runaway script is stopped instead of freezing the game
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 1024;
+const MAX_ARRAY_SIZE: usize = 1024;
+// Commands queued in one frame, the rest are dropped
+const MAX_COMMANDS: usize = 200;
+// Shortest period of `every` timers, in seconds
+const MIN_TIMER_PERIOD: f32 = 0.1;
+// Seconds a message stays when the script doesn't say
+const DEFAULT_MESSAGE_TIME: f32 = 4.0;
+
+// Game events passed to the hooks of the level script
+#[derive(Clone, Debug)]
+pub enum ScriptEvent {
+    // Wave index, from 0
+    WaveStart(u32),
+    // Unit type and team
+    UnitDeath(String, u32),
+}
+
+// Text of a script shown in `top-message` instead of the last transaction
+#[derive(Default)]
+pub struct ScriptMessage {
+    pub text: String,
+    // Game time in seconds
+    pub until: f32,
+}
+
+// Changes asked by the script, applied to the world by `ScriptSystem`
+enum ScriptCommand {
+    // Unit type and spawn point
+    SpawnUnit(String, INT),
+    // x, y, layer and tile id as in the map file, 0 clears the tile
+    SetTile(INT, INT, INT, INT),
+    GiveMoney(f32),
+    // Text or a string key after `@`, seconds
+    ShowMessage(String, f32),
+}
+
+struct ScriptTimer {
+    name: String,
+    // Game time in seconds
+    at: f32,
+    // Period of repeating timers
+    every: Option<f32>,
+}
+
+// All the script can see and change. Queries read the values copied here before
+// each frame, so scripts never hold world resources
+#[derive(Default)]
+struct ScriptShared {
+    commands: Vec<ScriptCommand>,
+    dropped: usize,
+    timers: Vec<ScriptTimer>,
+    now: f32,
+    // From 1, like the HUD
+    wave: u32,
+    waves: usize,
+    enemies: usize,
+    money: f32,
+    lives: u32,
+}
+
+impl ScriptShared {
+    // Values the query functions return until the next frame
+    fn update(&mut self, now: f32, log: &TransactionLog, level_info: &LevelInfo, user_data: &UserData, enemies: usize) {
+        self.now = now;
+        self.wave = log.wave + 1;
+        self.waves = level_info.levels.len();
+        self.enemies = enemies;
+        self.money = user_data.money;
+        self.lives = user_data.lives;
+    }
+
+    fn push(&mut self, command: ScriptCommand) {
+        if self.commands.len() < MAX_COMMANDS {
+            self.commands.push(command);
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    fn set_timer(&mut self, name: &str, seconds: f32, repeat: bool) {
+        self.timers.retain(|x| x.name != name);
+        let every = if repeat { Some(seconds.max(MIN_TIMER_PERIOD)) } else { None };
+        self.timers.push(ScriptTimer {
+            name: name.to_string(),
+            at: self.now + seconds.max(0.0),
+            every: every,
+        });
+    }
+
+    // Names of timers due at `now`, one shot timers are removed
+    fn due_timers(&mut self, now: f32) -> Vec<String> {
+        let mut due = Vec::new();
+        for timer in self.timers.iter_mut().filter(|x| x.at <= now) {
+            due.push(timer.name.clone());
+            // Missed periods are skipped, not fired one after another
+            if let Some(every) = timer.every {
+                timer.at += every;
+                if timer.at <= now {
+                    timer.at = now + every;
+                }
+            }
+        }
+        self.timers.retain(|x| x.every.is_some() || x.at > now);
+        due
+    }
+}
+
+// Functions a level script can call, nothing else of the game is reachable
+fn register_api(engine: &mut Engine, shared: &Arc<Mutex<ScriptShared>>) {
+    let s = shared.clone();
+    engine.register_fn("spawn_unit", move |name: &str| {
+        s.lock().unwrap().push(ScriptCommand::SpawnUnit(name.to_string(), 0));
+    });
+    let s = shared.clone();
+    engine.register_fn("spawn_unit", move |name: &str, spawn: INT| {
+        s.lock().unwrap().push(ScriptCommand::SpawnUnit(name.to_string(), spawn));
+    });
+    let s = shared.clone();
+    engine.register_fn("set_tile", move |x: INT, y: INT, tile: INT| {
+        s.lock().unwrap().push(ScriptCommand::SetTile(x, y, 0, tile));
+    });
+    let s = shared.clone();
+    engine.register_fn("set_tile", move |x: INT, y: INT, layer: INT, tile: INT| {
+        s.lock().unwrap().push(ScriptCommand::SetTile(x, y, layer, tile));
+    });
+    let s = shared.clone();
+    engine.register_fn("give_money", move |amount: INT| {
+        s.lock().unwrap().push(ScriptCommand::GiveMoney(amount as f32));
+    });
+    let s = shared.clone();
+    engine.register_fn("give_money", move |amount: FLOAT| {
+        s.lock().unwrap().push(ScriptCommand::GiveMoney(amount as f32));
+    });
+    let s = shared.clone();
+    engine.register_fn("show_message", move |text: &str| {
+        s.lock().unwrap().push(ScriptCommand::ShowMessage(text.to_string(), DEFAULT_MESSAGE_TIME));
+    });
+    let s = shared.clone();
+    engine.register_fn("show_message", move |text: &str, seconds: INT| {
+        s.lock().unwrap().push(ScriptCommand::ShowMessage(text.to_string(), seconds as f32));
+    });
+    let s = shared.clone();
+    engine.register_fn("show_message", move |text: &str, seconds: FLOAT| {
+        s.lock().unwrap().push(ScriptCommand::ShowMessage(text.to_string(), seconds as f32));
+    });
+
+    let s = shared.clone();
+    engine.register_fn("after", move |name: &str, seconds: INT| s.lock().unwrap().set_timer(name, seconds as f32, false));
+    let s = shared.clone();
+    engine.register_fn("after", move |name: &str, seconds: FLOAT| s.lock().unwrap().set_timer(name, seconds as f32, false));
+    let s = shared.clone();
+    engine.register_fn("every", move |name: &str, seconds: INT| s.lock().unwrap().set_timer(name, seconds as f32, true));
+    let s = shared.clone();
+    engine.register_fn("every", move |name: &str, seconds: FLOAT| s.lock().unwrap().set_timer(name, seconds as f32, true));
+    let s = shared.clone();
+    engine.register_fn("cancel", move |name: &str| s.lock().unwrap().timers.retain(|x| x.name != name));
+
+    let s = shared.clone();
+    engine.register_fn("wave", move || s.lock().unwrap().wave as INT);
+    let s = shared.clone();
+    engine.register_fn("waves", move || s.lock().unwrap().waves as INT);
+    let s = shared.clone();
+    engine.register_fn("enemies", move || s.lock().unwrap().enemies as INT);
+    let s = shared.clone();
+    engine.register_fn("money", move || s.lock().unwrap().money as FLOAT);
+    let s = shared.clone();
+    engine.register_fn("lives", move || s.lock().unwrap().lives as INT);
+}
+
+// Script of the loaded level. Hooks are functions it defines: on_start() when a run
+// of the level starts, on_wave_start(wave), on_unit_death(unit_type) and on_timer(name)
+pub struct LevelScript {
+    path: String,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    hooks: HashSet<String>,
+    shared: Arc<Mutex<ScriptShared>>,
+}
+
+// Engine with the game API and the sandbox limits, `path` names the script in printed text
+fn create_engine(path: &str, shared: &Arc<Mutex<ScriptShared>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_ARRAY_SIZE);
+    engine.set_max_map_size(MAX_ARRAY_SIZE);
+    // No files through `import` and no code built at runtime
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    let name = path.to_string();
+    engine.on_print(move |text| println!("{}: {}", name, text));
+    register_api(&mut engine, shared);
+    engine
+}
+
+impl LevelScript {
+    pub fn load(path: &str) -> Result<LevelScript, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+        let shared = Arc::new(Mutex::new(ScriptShared::default()));
+        let engine = create_engine(path, &shared);
+
+        let ast = engine.compile(&source).map_err(|e| format!("Failed to parse {}: {}", path, e))?;
+        let hooks = ast.iter_functions().map(|x| x.name.to_string()).collect::<HashSet<_>>();
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast).map_err(|e| format!("Failed to run {}: {}", path, e))?;
+
+        Ok(LevelScript {
+            path: path.to_string(),
+            engine: engine,
+            ast: ast,
+            scope: scope,
+            hooks: hooks,
+            shared: shared,
+        })
+    }
+
+    // Every run of the level starts without timers or queued commands,
+    // the top level statements run again before `on_start`
+    fn restart(&mut self, shared: ScriptShared) {
+        *self.shared.lock().unwrap() = shared;
+        self.scope = Scope::new();
+        if let Err(e) = self.engine.run_ast_with_scope(&mut self.scope, &self.ast) {
+            println!("Failed to run {}: {}", self.path, e);
+        }
+        self.call("on_start", ());
+    }
+
+    // Hooks the script doesn't define are skipped, errors are reported and the game goes on
+    fn call(&mut self, hook: &str, args: impl FuncArgs) {
+        if !self.hooks.contains(hook) {
+            return;
+        }
+        let options = CallFnOptions::new().eval_ast(false);
+        if let Err(e) = self.engine.call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, hook, args) {
+            println!("Script error in {} of {}: {}", hook, self.path, e);
+        }
+    }
+}
+
+pub fn initialise_script(world: &mut World) {
+    world.insert::<ScriptMessage>(ScriptMessage::default());
+    let path = match world.fetch::<LevelInfo>().script.clone() {
+        Some(e) => world.fetch::<Mods>().resolve(&e),
+        None => return,
+    };
+    let script = match LevelScript::load(&path) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    world.insert::<LevelScript>(script);
+}
+
+// Called when a run of the level starts, commands of `on_start` are applied on the next frame
+pub fn start_script(world: &mut World) {
+    world.insert::<ScriptMessage>(ScriptMessage::default());
+    let mut shared = ScriptShared::default();
+    shared.update(
+        world.fetch::<Time>().absolute_time_seconds() as f32,
+        &world.fetch::<TransactionLog>(),
+        &world.fetch::<LevelInfo>(),
+        &world.fetch::<UserData>(),
+        0,
+    );
+    if let Some(mut script) = world.try_fetch_mut::<LevelScript>() {
+        script.restart(shared);
+    }
+}
+
+// Called when a run of the level stops, timers don't fire outside of a run
+pub fn stop_script(world: &mut World) {
+    world.insert::<ScriptMessage>(ScriptMessage::default());
+    if let Some(script) = world.try_fetch::<LevelScript>() {
+        *script.shared.lock().unwrap() = ScriptShared::default();
+    }
+}
+
+// Runs the hooks of the level script and applies what they ask for
+#[derive(Default)]
+pub struct ScriptSystem {
+    reader_id: Option<ReaderId<ScriptEvent>>,
+}
+
+impl<'s> System<'s> for ScriptSystem {
+    type SystemData = (
+        Read<'s, EventChannel<ScriptEvent>>,
+        ReadStorage<'s, Unit>,
+        Read<'s, LevelInfo>,
+        Read<'s, UnitTyes>,
+        Read<'s, Locale>,
+        Read<'s, LazyUpdate>,
+        Read<'s, Time>,
+        Write<'s, MapData>,
+        Write<'s, TransactionLog>,
+        Write<'s, ScriptMessage>,
+        WriteExpect<'s, UserData>,
+        Option<WriteExpect<'s, LevelScript>>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<ScriptEvent>>().register_reader());
+    }
+
+    fn run(
+        &mut self,
+        (
+            script_events,
+            units,
+            level_info,
+            unit_types,
+            locale,
+            lazy_update,
+            time,
+            mut map_data,
+            mut log,
+            mut message,
+            mut user_data,
+            script,
+        ): Self::SystemData,
+    ) {
+        let events = script_events.read(self.reader_id.as_mut().unwrap()).cloned().collect::<Vec<_>>();
+        let mut script = match script {
+            Some(e) => e,
+            None => return,
+        };
+
+        // Game time, timers stop while the game is paused
+        let now = time.absolute_time_seconds() as f32;
+        let due = {
+            let mut shared = script.shared.lock().unwrap();
+            let enemies = units.join().filter(|x| x.team != PLAYER_TEAM).count();
+            shared.update(now, &log, &level_info, &user_data, enemies);
+            shared.due_timers(now)
+        };
+
+        for event in events {
+            match event {
+                ScriptEvent::WaveStart(wave) => script.call("on_wave_start", (wave as INT + 1,)),
+                ScriptEvent::UnitDeath(unit_type, team) if team != PLAYER_TEAM => script.call("on_unit_death", (unit_type,)),
+                _ => (),
+            }
+        }
+        for name in due {
+            script.call("on_timer", (name,));
+        }
+
+        let (commands, dropped) = {
+            let mut shared = script.shared.lock().unwrap();
+            let dropped = shared.dropped;
+            shared.dropped = 0;
+            (shared.commands.drain(..).collect::<Vec<_>>(), dropped)
+        };
+        if dropped > 0 {
+            println!("{} dropped {} commands over the limit of {} per frame", script.path, dropped, MAX_COMMANDS);
+        }
+
+        for command in commands {
+            match command {
+                ScriptCommand::SpawnUnit(unit_type, spawn) => {
+                    let spawns = level_info.enemy_spawn.len() as INT;
+                    if !unit_types.types.iter().any(|x| x.name == unit_type) {
+                        println!("{}: unknown unit type {}", script.path, unit_type);
+                    } else if spawn < 0 || spawn >= spawns {
+                        println!("{}: spawn point {} is not in 0..{}", script.path, spawn, spawns);
+                    } else {
+                        lazy_update.exec_mut(move |world| {
+                            spawn_unit_type(world, &unit_type, spawn as usize);
+                        });
+                    }
+                },
+                ScriptCommand::SetTile(x, y, layer, tile) => {
+                    let inside = x >= 0 && y >= 0 && layer >= 0
+                        && x < map_data.width as INT && y < map_data.height as INT
+                        && layer < map_data.layers.len() as INT;
+                    if inside && tile >= 0 {
+                        map_data.change_id_on_point(Point3::new(x as u32, y as u32, layer as u32), tile as u32);
+                        lazy_update.exec_mut(move |world| refresh_minimap_cell(world, x as u32, y as u32));
+                    } else {
+                        println!("{}: can't set tile {} at {}, {} on layer {}", script.path, tile, x, y, layer);
+                    }
+                },
+                ScriptCommand::GiveMoney(amount) => {
+                    credit(&mut user_data, &mut log, &time, amount, Reason::Script);
+                },
+                ScriptCommand::ShowMessage(text, seconds) => {
+                    message.text = if text.starts_with(KEY_PREFIX) {
+                        locale.tr(&text[KEY_PREFIX.len_utf8()..])
+                    } else {
+                        text
+                    };
+                    message.until = now + seconds;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhai::EvalAltResult;
+
+    fn engine() -> Engine {
+        create_engine("test.rhai", &Arc::new(Mutex::new(ScriptShared::default())))
+    }
+
+    fn run_error(source: &str) -> EvalAltResult {
+        *engine().run(source).unwrap_err()
+    }
+
+    #[test]
+    fn one_shot_timer_fires_once() {
+        let mut shared = ScriptShared::default();
+        shared.set_timer("door", 2.0, false);
+        assert!(shared.due_timers(1.0).is_empty());
+        assert_eq!(shared.due_timers(2.0), vec!["door".to_string()]);
+        assert!(shared.due_timers(10.0).is_empty());
+        assert!(shared.timers.is_empty());
+    }
+
+    #[test]
+    fn repeating_timer_fires_every_period() {
+        let mut shared = ScriptShared {
+            now: 1.0,
+            ..Default::default()
+        };
+        shared.set_timer("tick", 1.0, true);
+        assert!(shared.due_timers(1.5).is_empty());
+        assert_eq!(shared.due_timers(2.0), vec!["tick".to_string()]);
+        assert!(shared.due_timers(2.5).is_empty());
+        assert_eq!(shared.due_timers(3.0), vec!["tick".to_string()]);
+        // A long frame fires it once, not once for every missed period
+        assert_eq!(shared.due_timers(10.0), vec!["tick".to_string()]);
+        assert!(shared.due_timers(10.5).is_empty());
+        assert_eq!(shared.due_timers(11.0), vec!["tick".to_string()]);
+    }
+
+    #[test]
+    fn timer_period_has_a_minimum_and_names_are_unique() {
+        let mut shared = ScriptShared::default();
+        shared.set_timer("tick", 0.0, true);
+        shared.set_timer("tick", 0.0, true);
+        assert_eq!(shared.timers.len(), 1);
+        assert_eq!(shared.timers[0].every, Some(MIN_TIMER_PERIOD));
+    }
+
+    #[test]
+    fn commands_over_the_limit_are_dropped() {
+        let mut shared = ScriptShared::default();
+        for _ in 0..MAX_COMMANDS + 5 {
+            shared.push(ScriptCommand::GiveMoney(1.0));
+        }
+        assert_eq!(shared.commands.len(), MAX_COMMANDS);
+        assert_eq!(shared.dropped, 5);
+    }
+
+    #[test]
+    fn endless_loop_is_stopped() {
+        assert!(matches!(run_error("loop {}"), EvalAltResult::ErrorTooManyOperations(_)));
+    }
+
+    #[test]
+    fn deep_recursion_is_stopped() {
+        assert!(matches!(run_error("fn down(n) { down(n + 1) } down(0);"), EvalAltResult::ErrorStackOverflow(_)));
+    }
+
+    #[test]
+    fn large_strings_and_arrays_are_stopped() {
+        assert!(matches!(
+            run_error("let s = \"x\"; loop { s += s; }"),
+            EvalAltResult::ErrorDataTooLarge(..)
+        ));
+        assert!(matches!(
+            run_error("let a = []; loop { a.push(1); }"),
+            EvalAltResult::ErrorDataTooLarge(..)
+        ));
+    }
+
+    #[test]
+    fn eval_and_import_are_not_available() {
+        assert!(engine().compile("eval(\"1\")").is_err());
+        assert!(engine().run("import \"helpers\" as helpers;").is_err());
+    }
+
+    #[test]
+    fn api_calls_queue_commands_and_timers() {
+        let shared = Arc::new(Mutex::new(ScriptShared::default()));
+        let engine = create_engine("test.rhai", &shared);
+        engine.run("spawn_unit(\"bug\", 1); give_money(5); every(\"tick\", 2.0); after(\"once\", 1); cancel(\"once\");").unwrap();
+
+        let shared = shared.lock().unwrap();
+        assert_eq!(shared.commands.len(), 2);
+        assert!(matches!(&shared.commands[0], ScriptCommand::SpawnUnit(name, 1) if name == "bug"));
+        assert_eq!(shared.timers.len(), 1);
+        assert_eq!(shared.timers[0].name, "tick");
+    }
+}
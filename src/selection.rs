@@ -0,0 +1,102 @@
+use amethyst::{
+    assets::AssetStorage,
+    core::{math::Point2, Transform},
+    ecs::{Entities, Entity, Read, ReadStorage, System, Write},
+    input::{InputHandler, StringBindings},
+    renderer::{debug_drawing::DebugLines, SpriteRender, SpriteSheet},
+};
+use amethyst_rendy::palette::Srgba;
+use crate::camera::CursorPosition;
+use crate::camera_director::CameraDirector;
+use crate::tower::Tower;
+use crate::unit::Unit;
+use crate::utils::sprite_size;
+
+// Unit or tower which was clicked by the player
+#[derive(Default)]
+pub struct Selection {
+    pub entity: Option<Entity>,
+}
+
+fn draw_outline(
+    debug_lines: &mut DebugLines,
+    transform: &Transform,
+    size: (f32, f32),
+    colour: Srgba,
+) {
+    let (x, y) = (transform.translation().x, transform.translation().y);
+    debug_lines.draw_rectangle(
+        Point2::new(x - size.0 * 0.5, y - size.1 * 0.5),
+        Point2::new(x + size.0 * 0.5, y + size.1 * 0.5),
+        transform.translation().z + 0.1,
+        colour,
+    );
+}
+
+// Outlines hovered and selected sprites, `follow_selection` makes the camera follow the selection
+#[derive(Default)]
+pub struct SelectionSystem {
+    follow_down: bool,
+}
+
+impl<'s> System<'s> for SelectionSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Unit>,
+        ReadStorage<'s, Tower>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, SpriteRender>,
+        Read<'s, AssetStorage<SpriteSheet>>,
+        Read<'s, CursorPosition>,
+        Read<'s, InputHandler<StringBindings>>,
+        Write<'s, Selection>,
+        Write<'s, CameraDirector>,
+        Write<'s, DebugLines>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            units,
+            towers,
+            transforms,
+            sprites,
+            sprite_sheets,
+            cursor,
+            input,
+            mut selection,
+            mut director,
+            mut debug_lines,
+        ): Self::SystemData,
+    ) {
+        let selectable = |e: &Entity| entities.is_alive(*e) && (units.contains(*e) || towers.contains(*e));
+        selection.entity = selection.entity.filter(selectable);
+
+        let follow_down = input.action_is_down("follow_selection").unwrap_or(false);
+        if follow_down && !self.follow_down {
+            if director.following().is_some() && director.following() == selection.entity {
+                director.follow(None);
+            } else {
+                director.follow(selection.entity);
+            }
+        }
+        self.follow_down = follow_down;
+
+        let hovered = cursor.hovered.filter(selectable).filter(|e| Some(*e) != selection.entity);
+        let outlines = [
+            (selection.entity, Srgba::new(1.0, 1.0, 0.0, 1.0)),
+            (hovered, Srgba::new(1.0, 1.0, 1.0, 0.6)),
+        ];
+        for (entity, colour) in outlines.iter() {
+            let entity = match entity {
+                Some(e) => *e,
+                None => continue,
+            };
+            let size = sprites.get(entity).and_then(|s| sprite_size(&sprite_sheets, s));
+            if let (Some(transform), Some(size)) = (transforms.get(entity), size) {
+                draw_outline(&mut debug_lines, transform, size, *colour);
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use amethyst::{
+    prelude::*,
+    utils::application_root_dir,
+    window::Window,
+};
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use crate::audio::Volumes;
+use crate::display::{apply_window_settings, WindowMode};
+use crate::game_speed::GameSpeed;
+
+// Window sizes offered in the settings screen
+pub const RESOLUTIONS: [(u32, u32); 4] = [(1200, 800), (1280, 720), (1600, 900), (1920, 1080)];
+pub const CAMERA_SPEEDS: [f32; 5] = [300.0, 450.0, 600.0, 900.0, 1200.0];
+
+// Player settings from config/settings.ron, missing fields keep their defaults
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub volumes: Volumes,
+    pub window_mode: WindowMode,
+    // Windowed size in logical pixels, None keeps the size from display.ron
+    pub resolution: Option<(u32, u32)>,
+    // World units per second
+    pub camera_speed: f32,
+    pub edge_scrolling: bool,
+    pub health_bars: bool,
+    pub language: String,
+    // Game speed multiplier when the game starts
+    pub game_speed: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            volumes: Volumes::default(),
+            window_mode: WindowMode::Windowed,
+            resolution: None,
+            camera_speed: 600.0,
+            edge_scrolling: true,
+            health_bars: true,
+            language: "en".to_string(),
+            game_speed: 1.0,
+        }
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    match application_root_dir() {
+        Ok(e) => Some(e.join("config").join("settings.ron")),
+        Err(e) => {
+            println!("Can't find config directory: {}", e);
+            None
+        }
+    }
+}
+
+// Defaults are used until the settings are saved for the first time
+pub fn load_settings() -> Settings {
+    let path = match settings_path() {
+        Some(e) => e,
+        None => return Settings::default(),
+    };
+    let data = match fs::read_to_string(&path) {
+        Ok(e) => e,
+        Err(_) => return Settings::default(),
+    };
+    match ron::de::from_str::<Settings>(&data) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Failed to parse {}: {}", path.display(), e);
+            Settings::default()
+        }
+    }
+}
+
+pub fn save_settings(settings: &Settings) {
+    let path = match settings_path() {
+        Some(e) => e,
+        None => return,
+    };
+    let data = match to_string_pretty(settings, PrettyConfig::default()) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't serialize settings: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = fs::write(&path, data) {
+        println!("Can't write {}: {}", path.display(), e);
+    }
+}
+
+// Must run before `initialise_camera`, so the camera is made for the final window size.
+// Later changes are applied by the systems reading `Settings`
+pub fn initialise_settings(world: &mut World) {
+    let settings = load_settings();
+    apply_window_settings(&world.read_resource::<Window>(), settings.window_mode, settings.resolution);
+    world.write_resource::<GameSpeed>().set(settings.game_speed);
+    world.insert::<Settings>(settings);
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+
+// Multiplicative hash, keys are small ids so the default SipHash is a waste here
+#[derive(Default)]
+pub struct IdHasher(u64);
+
+impl Hasher for IdHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0.rotate_left(5) ^ i).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+}
+
+// Uniform grid of buckets, items are stored by their position only.
+// Positions outside of the grid go to the border cells.
+// Kept free of engine types, the lib exports it for `spatial_bench`
+pub struct SpatialGrid<T> {
+    // World position of the corner of cell (0, 0)
+    origin: (f32, f32),
+    cell_size: (f32, f32),
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<(T, f32, f32)>>,
+    // Cell and position in the cell bucket of every stored item
+    items: HashMap<T, (usize, usize), BuildHasherDefault<IdHasher>>,
+}
+
+impl<T: Copy + Eq + Hash> SpatialGrid<T> {
+    pub fn new(origin: (f32, f32), cell_size: (f32, f32), columns: usize, rows: usize) -> SpatialGrid<T> {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        SpatialGrid {
+            origin: origin,
+            cell_size: (cell_size.0.max(1.0), cell_size.1.max(1.0)),
+            columns: columns,
+            rows: rows,
+            cells: (0..columns * rows).map(|_| Vec::new()).collect(),
+            items: HashMap::default(),
+        }
+    }
+
+    // Column and row of the cell containing the point
+    pub fn cell_of(&self, x: f32, y: f32) -> (usize, usize) {
+        let column = ((x - self.origin.0) / self.cell_size.0).floor().max(0.0) as usize;
+        let row = ((y - self.origin.1) / self.cell_size.1).floor().max(0.0) as usize;
+        (column.min(self.columns - 1), row.min(self.rows - 1))
+    }
+
+    fn cell_index(&self, x: f32, y: f32) -> usize {
+        let (column, row) = self.cell_of(x, y);
+        row * self.columns + column
+    }
+
+    // Inserts a new item or moves an existing one
+    pub fn insert(&mut self, item: T, x: f32, y: f32) {
+        let cell = self.cell_index(x, y);
+        match self.items.get(&item).copied() {
+            Some((old, slot)) if old == cell => {
+                self.cells[cell][slot] = (item, x, y);
+                return;
+            },
+            Some(_) => self.remove(item),
+            None => (),
+        }
+        self.items.insert(item, (cell, self.cells[cell].len()));
+        self.cells[cell].push((item, x, y));
+    }
+
+    pub fn remove(&mut self, item: T) {
+        let (cell, slot) = match self.items.remove(&item) {
+            Some(e) => e,
+            None => return,
+        };
+        let bucket = &mut self.cells[cell];
+        bucket.swap_remove(slot);
+        // Last item of the bucket took the free slot
+        if let Some(moved) = bucket.get(slot) {
+            self.items.insert(moved.0, (cell, slot));
+        }
+    }
+
+    // Items in the cell containing the point
+    pub fn query_point(&self, x: f32, y: f32) -> Vec<T> {
+        self.cells[self.cell_index(x, y)].iter().map(|e| e.0).collect()
+    }
+
+    // Items with positions inside the rectangle, bounds included
+    pub fn query_rect(&self, min: (f32, f32), max: (f32, f32)) -> Vec<T> {
+        let mut found = Vec::new();
+        self.visit_rect(min, max, |item, _, _| found.push(item));
+        found
+    }
+
+    // Items not further than `radius` from the point
+    pub fn query_radius(&self, x: f32, y: f32, radius: f32) -> Vec<T> {
+        let mut found = Vec::new();
+        let radius_sq = radius * radius;
+        self.visit_rect((x - radius, y - radius), (x + radius, y + radius), |item, item_x, item_y| {
+            let (dx, dy) = (item_x - x, item_y - y);
+            if dx * dx + dy * dy <= radius_sq {
+                found.push(item);
+            }
+        });
+        found
+    }
+
+    fn visit_rect<F: FnMut(T, f32, f32)>(&self, min: (f32, f32), max: (f32, f32), mut visit: F) {
+        let (min_column, min_row) = self.cell_of(min.0, min.1);
+        let (max_column, max_row) = self.cell_of(max.0, max.1);
+
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                for &(item, x, y) in &self.cells[row * self.columns + column] {
+                    if x >= min.0 && x <= max.0 && y >= min.1 && y <= max.1 {
+                        visit(item, x, y);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10 x 10 cells of 32 pixels from the origin
+    fn grid() -> SpatialGrid<u32> {
+        let mut grid = SpatialGrid::new((0.0, 0.0), (32.0, 32.0), 10, 10);
+        grid.insert(1, 10.0, 10.0);
+        grid.insert(2, 20.0, 20.0);
+        grid.insert(3, 100.0, 100.0);
+        grid.insert(4, 300.0, 10.0);
+        grid
+    }
+
+    fn sorted(mut items: Vec<u32>) -> Vec<u32> {
+        items.sort();
+        items
+    }
+
+    #[test]
+    fn point_query_returns_the_cell_items() {
+        let grid = grid();
+        assert_eq!(sorted(grid.query_point(0.0, 31.0)), vec![1, 2]);
+        assert_eq!(grid.query_point(96.0, 96.0), vec![3]);
+        assert!(grid.query_point(200.0, 200.0).is_empty());
+    }
+
+    #[test]
+    fn positions_outside_go_to_the_border_cells() {
+        let mut grid = grid();
+        grid.insert(5, -50.0, 1000.0);
+        assert_eq!(grid.cell_of(-50.0, 1000.0), (0, 9));
+        assert_eq!(grid.query_point(0.0, 300.0), vec![5]);
+    }
+
+    #[test]
+    fn radius_query_checks_the_distance() {
+        let grid = grid();
+        assert_eq!(sorted(grid.query_radius(15.0, 15.0, 8.0)), vec![1, 2]);
+        assert_eq!(grid.query_radius(10.0, 10.0, 5.0), vec![1]);
+        assert_eq!(sorted(grid.query_radius(60.0, 60.0, 75.0)), vec![1, 2, 3]);
+        assert!(grid.query_radius(200.0, 200.0, 50.0).is_empty());
+    }
+
+    #[test]
+    fn rect_query_includes_the_bounds() {
+        let grid = grid();
+        assert_eq!(sorted(grid.query_rect((10.0, 10.0), (100.0, 100.0))), vec![1, 2, 3]);
+        assert_eq!(grid.query_rect((11.0, 0.0), (99.0, 99.0)), vec![2]);
+        assert_eq!(grid.query_rect((250.0, 0.0), (320.0, 20.0)), vec![4]);
+    }
+
+    #[test]
+    fn moved_and_removed_items_are_updated() {
+        let mut grid = grid();
+        grid.insert(1, 100.0, 110.0);
+        assert_eq!(grid.query_point(10.0, 10.0), vec![2]);
+        assert_eq!(sorted(grid.query_point(100.0, 100.0)), vec![1, 3]);
+
+        grid.remove(3);
+        grid.remove(42);
+        assert_eq!(grid.query_point(100.0, 100.0), vec![1]);
+        assert_eq!(grid.query_radius(100.0, 110.0, 1.0), vec![1]);
+    }
+}
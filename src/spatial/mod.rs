@@ -0,0 +1,105 @@
+use amethyst::{
+    core::Transform,
+    ecs::{
+        storage::ComponentEvent, BitSet, Entities, Entity, Join, Read, ReadStorage, ReaderId, System,
+        SystemData, Write, WriteStorage,
+    },
+    prelude::*,
+};
+use crate::map::MapData;
+use crate::tower::Tower;
+use crate::unit::Unit;
+use td_amethyst::spatial::grid::SpatialGrid;
+
+// Sprites are picked among indexed entities this close to the mouse
+pub const PICK_MARGIN: f32 = 64.0;
+
+// Positions of units and towers in a grid with the tile size of the map
+#[derive(Default)]
+pub struct SpatialIndex {
+    grid: Option<SpatialGrid<u32>>,
+    // Map width, height and tile size the grid was built for
+    map_size: (u32, u32, u32, u32),
+}
+
+impl SpatialIndex {
+    fn to_entities(entities: &Entities<'_>, ids: Vec<u32>) -> Vec<Entity> {
+        ids.into_iter()
+            .map(|id| entities.entity(id))
+            .filter(|e| entities.is_alive(*e))
+            .collect()
+    }
+
+    pub fn query_point(&self, entities: &Entities<'_>, x: f32, y: f32) -> Vec<Entity> {
+        self.grid.as_ref().map_or(Vec::new(), |g| SpatialIndex::to_entities(entities, g.query_point(x, y)))
+    }
+
+    pub fn query_rect(&self, entities: &Entities<'_>, min: (f32, f32), max: (f32, f32)) -> Vec<Entity> {
+        self.grid.as_ref().map_or(Vec::new(), |g| SpatialIndex::to_entities(entities, g.query_rect(min, max)))
+    }
+
+    pub fn query_radius(&self, entities: &Entities<'_>, x: f32, y: f32, radius: f32) -> Vec<Entity> {
+        self.grid.as_ref().map_or(Vec::new(), |g| SpatialIndex::to_entities(entities, g.query_radius(x, y, radius)))
+    }
+}
+
+// Follows `Transform` changes of units and towers
+#[derive(Default)]
+pub struct SpatialIndexSystem {
+    reader_id: Option<ReaderId<ComponentEvent>>,
+    dirty: BitSet,
+}
+
+impl<'s> System<'s> for SpatialIndexSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Unit>,
+        ReadStorage<'s, Tower>,
+        Read<'s, MapData>,
+        Write<'s, SpatialIndex>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(WriteStorage::<Transform>::fetch(world).register_reader());
+    }
+
+    fn run(&mut self, (entities, transforms, units, towers, map_data, mut index): Self::SystemData) {
+        self.dirty.clear();
+        for event in transforms.channel().read(self.reader_id.as_mut().unwrap()) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) | ComponentEvent::Removed(id) => {
+                    self.dirty.add(*id);
+                },
+            }
+        }
+
+        // New map or first run, index everything again
+        let map_size = (map_data.width, map_data.height, map_data.tilewidth, map_data.tileheight);
+        if index.grid.is_none() || index.map_size != map_size {
+            let cell_size = (map_data.tilewidth as f32, map_data.tileheight as f32);
+            let origin = (
+                -((map_data.width * map_data.tilewidth) as f32) * 0.5,
+                -((map_data.height * map_data.tileheight) as f32) * 0.5,
+            );
+            index.grid = Some(SpatialGrid::new(
+                origin, cell_size, map_data.width as usize, map_data.height as usize,
+            ));
+            index.map_size = map_size;
+            for (entity, _) in (&entities, &transforms).join() {
+                self.dirty.add(entity.id());
+            }
+        }
+
+        let grid = index.grid.as_mut().unwrap();
+        for id in (&self.dirty).join() {
+            let entity = entities.entity(id);
+            let indexed = entities.is_alive(entity) && (units.contains(entity) || towers.contains(entity));
+            match transforms.get(entity).filter(|_| indexed) {
+                Some(transform) => grid.insert(id, transform.translation().x, transform.translation().y),
+                None => grid.remove(id),
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+extern crate amethyst;
+use amethyst::prelude::*;
+use amethyst::{
+    assets::Loader,
+    ecs::Entity,
+    input::{is_close_requested, Button, InputEvent, InputHandler, StringBindings, VirtualKeyCode},
+    ui::{Anchor, Interactable, TtfFormat, UiEventType, UiText, UiTransform},
+    utils::application_root_dir,
+};
+use ron::ser::{to_string_pretty, PrettyConfig};
+use std::fs;
+use crate::locale::Locale;
+
+// Lists every action, clicking one waits for the next key or mouse button
+// and binds it instead of the old ones. Changes are written to config/bindings.ron
+#[derive(Default)]
+pub struct BindingsState {
+    // Action name and its label
+    labels: Vec<(String, Entity)>,
+    title: Option<Entity>,
+    waiting: Option<String>,
+    // Set when a button was taken for a binding, so its action is ignored this frame
+    captured: bool,
+}
+
+fn binding_text(locale: &Locale, input: &InputHandler<StringBindings>, action: &String) -> String {
+    let combos = input.bindings.action_bindings(action)
+        .map(|combo| combo.iter().map(|b| format!("{:?}", b)).collect::<Vec<_>>().join(" + "))
+        .collect::<Vec<_>>();
+    locale.tr_with("bindings.binding", &[("action", locale.data_name("action", action)), ("buttons", combos.join(", "))])
+}
+
+fn save_bindings(input: &InputHandler<StringBindings>) {
+    let path = match application_root_dir() {
+        Ok(e) => e.join("config").join("bindings.ron"),
+        Err(e) => {
+            println!("Can't find config directory: {}", e);
+            return;
+        }
+    };
+    let data = match to_string_pretty(&input.bindings, PrettyConfig::default()) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't serialize bindings: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = fs::write(&path, data) {
+        println!("Can't write {}: {}", path.display(), e);
+    }
+}
+
+impl BindingsState {
+    fn set_text(&self, world: &World, entity: Entity, text: String) {
+        if let Some(ui_text) = world.write_storage::<UiText>().get_mut(entity) {
+            ui_text.text = text;
+        }
+    }
+
+    fn refresh(&self, world: &World) {
+        let input = world.fetch::<InputHandler<StringBindings>>();
+        let locale = world.fetch::<Locale>();
+        for (action, entity) in self.labels.iter() {
+            let text = match &self.waiting {
+                Some(e) if e == action => locale.tr_with("bindings.waiting", &[("action", locale.data_name("action", action))]),
+                _ => binding_text(&locale, &input, action),
+            };
+            self.set_text(world, *entity, text);
+        }
+    }
+
+    fn rebind(&self, world: &World, action: String, button: Button) {
+        let mut input = world.write_resource::<InputHandler<StringBindings>>();
+        let old = input.bindings.action_bindings(&action)
+            .map(|combo| combo.to_vec())
+            .collect::<Vec<_>>();
+        for combo in old.iter() {
+            input.bindings.remove_action_binding(&action, combo);
+        }
+
+        if let Err(e) = input.bindings.insert_action_binding(action.clone(), vec![button]) {
+            println!("Can't bind {:?} to {}: {:?}", button, action, e);
+            for combo in old {
+                input.bindings.insert_action_binding(action.clone(), combo).unwrap();
+            }
+            return;
+        }
+        save_bindings(&input);
+    }
+}
+
+impl SimpleState for BindingsState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let font = world.read_resource::<Loader>().load(
+            "font/square.ttf", TtfFormat, (), &world.read_resource(),
+        );
+
+        let mut actions = world.fetch::<InputHandler<StringBindings>>().bindings
+            .actions()
+            .cloned()
+            .collect::<Vec<_>>();
+        actions.sort();
+
+        let title = world
+            .create_entity()
+            .with(UiText::new(font.clone(), "@bindings.title".to_string(), [1.0, 1.0, 0.0, 1.0], 25.0))
+            .with(UiTransform::new(
+                "bindings-title".to_string(), Anchor::TopMiddle, Anchor::Middle,
+                0.0, -40.0, 300.0, 500.0, 30.0,
+            ))
+            .build();
+        self.title = Some(title);
+
+        for (i, action) in actions.into_iter().enumerate() {
+            let entity = world
+                .create_entity()
+                .with(UiText::new(font.clone(), "".to_string(), [1.0, 1.0, 1.0, 1.0], 18.0))
+                .with(UiTransform::new(
+                    format!("bindings-{}", action), Anchor::TopMiddle, Anchor::Middle,
+                    0.0, -80.0 - i as f32 * 24.0, 300.0, 500.0, 24.0,
+                ))
+                .with(Interactable)
+                .build();
+            self.labels.push((action, entity));
+        }
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let entities = self.labels.drain(..).map(|(_, e)| e).chain(self.title.take());
+        data.world.delete_entities(&entities.collect::<Vec<_>>()).unwrap();
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        match &event {
+            StateEvent::Window(event) => {
+                if is_close_requested(&event) {
+                    return Trans::Quit;
+                }
+            },
+            StateEvent::Input(InputEvent::ButtonPressed(button)) => {
+                if let Some(action) = self.waiting.take() {
+                    self.captured = true;
+                    if *button != Button::Key(VirtualKeyCode::Escape) {
+                        self.rebind(data.world, action, *button);
+                    }
+                    self.refresh(data.world);
+                }
+            },
+            // Actions close the screen only when no key is awaited
+            StateEvent::Input(InputEvent::ActionPressed(action)) if self.waiting.is_none() && !self.captured => {
+                if action == "quit" || action == "bindings_menu" {
+                    return Trans::Pop;
+                }
+            },
+            StateEvent::Ui(ui_event) if ui_event.event_type == UiEventType::Click => {
+                let clicked = self.labels.iter()
+                    .find(|(_, e)| *e == ui_event.target)
+                    .map(|(action, _)| action.clone());
+                if clicked.is_some() {
+                    self.waiting = clicked;
+                    self.refresh(data.world);
+                }
+            },
+            _ => (),
+        }
+
+        Trans::None
+    }
+
+    fn update(&mut self, _data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        self.captured = false;
+        Trans::None
+    }
+}
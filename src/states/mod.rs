@@ -1 +1,3 @@
 pub mod play;
+pub mod bindings;
+pub mod settings;
@@ -1,12 +1,26 @@
 extern crate amethyst;
 use amethyst::prelude::*;
 use amethyst::{
-    winit::VirtualKeyCode,
-    input::{is_close_requested, is_key_down},
+    input::{is_close_requested, InputEvent},
     core::Time,
+    ecs::Join,
+    shrev::EventChannel,
+    ui::{UiEventType, UiFinder, UiText},
 };
-use crate::unit::spawn_unit;
+use crate::unit::{spawn_unit, Unit};
+use crate::audio::SoundEvent;
+use crate::script::{start_script, stop_script, ScriptEvent};
 use crate::map::{LevelInfo};
+use crate::economy::{initialise_economy, pay_wave_income, TransactionLog};
+use crate::tower::{Tower, PLAYER_TEAM};
+use crate::difficulty::Difficulty;
+use crate::endless::{ensure_wave, EndlessMode};
+use crate::selection::Selection;
+use crate::stats::record_run;
+use crate::UserData;
+use crate::states::bindings::BindingsState;
+use crate::states::settings::SettingsState;
+use crate::locale::{Locale, Localized};
 // use log::info;
 
 pub struct PlayState {
@@ -25,36 +39,154 @@ impl PlayState {
     }
 }
 
+fn set_top_center(world: &World, text: String) {
+    let entity = match world.exec(|ui_finder: UiFinder<'_>| { ui_finder.find("top-center") }) {
+        Some(e) => e,
+        None => return,
+    };
+    // Text is set here from now on, not from its prefab key
+    world.write_storage::<Localized>().remove(entity);
+    if let Some(ui_text) = world.write_storage::<UiText>().get_mut(entity) {
+        if ui_text.text != text {
+            ui_text.text = text;
+        }
+    }
+}
+
+impl PlayState {
+    // Wave number and enemies alive or still to spawn
+    fn show_wave(&self, world: &World) {
+        let text = {
+            let locale = world.fetch::<Locale>();
+            let wave = if world.fetch::<EndlessMode>().enabled {
+                locale.tr_with("hud.wave_endless", &[("wave", (self.wave + 1).to_string())])
+            } else {
+                let waves = world.fetch::<LevelInfo>().levels.len();
+                locale.tr_with("hud.wave", &[("wave", (self.wave + 1).to_string()), ("waves", waves.to_string())])
+            };
+            let to_spawn = world.fetch::<LevelInfo>().get_units_count(self.wave).saturating_sub(self.index_spawn);
+            let alive = world.read_storage::<Unit>().join().filter(|x| x.team != PLAYER_TEAM).count();
+            let enemies = locale.tr_count("hud.enemies_left", to_spawn as i64 + alive as i64, &[]);
+            locale.tr_with("hud.wave_progress", &[("wave", wave), ("enemies", enemies)])
+        };
+        set_top_center(world, text);
+    }
+
+    fn enemies_left(world: &World) -> bool {
+        world.read_storage::<Unit>().join().any(|x| x.team != PLAYER_TEAM)
+    }
+
+    fn finish_level(&self, world: &World, won: bool) -> SimpleTrans {
+        let text = world.fetch::<Locale>().tr(if won { "hud.victory" } else { "hud.defeat" });
+        set_top_center(world, text);
+        record_run(world, won);
+        Trans::Pop
+    }
+}
+
+// The next run of the level starts over: towers and units are removed, money and lives
+// come from the level info again and generated endless waves are dropped
+fn reset_level(world: &mut World) {
+    let entities = {
+        let entities = world.entities();
+        let units = world.read_storage::<Unit>();
+        let towers = world.read_storage::<Tower>();
+        (&*entities, &units).join().map(|(e, _)| e)
+            .chain((&*entities, &towers).join().map(|(e, _)| e))
+            .collect::<Vec<_>>()
+    };
+    world.delete_entities(&entities).unwrap();
+    world.insert::<Selection>(Selection::default());
+    let authored_waves = world.fetch::<EndlessMode>().authored_waves;
+    world.write_resource::<LevelInfo>().levels.truncate(authored_waves);
+    initialise_economy(world);
+}
+
 impl SimpleState for PlayState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.write_resource::<TransactionLog>().wave = self.wave;
+        start_script(data.world);
+        self.show_wave(data.world);
+    }
+
+    // Towers built before the first wave are kept, so the level is reset when a run ends
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        stop_script(data.world);
+        reset_level(data.world);
+    }
+
     fn handle_event(
         &mut self,
-        _data: StateData<'_, GameData<'_, '_>>,
+        data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent,
     ) -> SimpleTrans {
-        if let StateEvent::Window(event) = &event {
-            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
-                return Trans::Quit;
-            }
+        match &event {
+            StateEvent::Window(event) => {
+                if is_close_requested(&event) {
+                    return Trans::Quit;
+                }
+            },
+            StateEvent::Input(InputEvent::ActionPressed(action)) => match action.as_str() {
+                "quit" => return Trans::Quit,
+                "bindings_menu" => return Trans::Push(Box::new(BindingsState::default())),
+                "settings_menu" => return Trans::Push(Box::new(SettingsState::default())),
+                _ => (),
+            },
+            StateEvent::Ui(ui_event) if ui_event.event_type == UiEventType::Click => {
+                let settings = data.world.exec(|ui_finder: UiFinder<'_>| ui_finder.find("settings"));
+                if settings == Some(ui_event.target) {
+                    return Trans::Push(Box::new(SettingsState::default()));
+                }
+            },
+            _ => (),
         }
 
         return Trans::None;
     }
 
     fn fixed_update(&mut self, data: StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        let current_time = data.world.fetch::<Time>().absolute_time().as_millis();
-
-        // If pass enough time from last spawn
-        let spawn_speed = data.world.fetch::<LevelInfo>().spawn_speed;
-        if self.last_spawn_time + spawn_speed as u128 <= current_time {
+        if data.world.fetch::<UserData>().lives == 0 {
+            return self.finish_level(data.world, false);
+        }
 
-            // If any units left to spawn
-            if self.index_spawn < data.world.fetch::<LevelInfo>().get_units_count(self.wave) {
+        // Absolute time is scaled by the game speed, so spawning follows it
+        let current_time = data.world.fetch::<Time>().absolute_time().as_millis();
+        let units_count = data.world.fetch::<LevelInfo>().get_units_count(self.wave);
 
-                spawn_unit(data.world, 0, self.index_spawn);
+        // If any units left to spawn
+        if self.index_spawn < units_count {
+            // If pass enough time from last spawn
+            let spawn_speed = data.world.fetch::<LevelInfo>().spawn_speed as f32
+                * data.world.fetch::<Difficulty>().spawn_interval;
+            if self.last_spawn_time + spawn_speed as u128 <= current_time {
+                if self.index_spawn == 0 {
+                    data.world.write_resource::<EventChannel<SoundEvent>>().single_write(SoundEvent::WaveStart);
+                    data.world.write_resource::<EventChannel<ScriptEvent>>().single_write(ScriptEvent::WaveStart(self.wave));
+                }
+                spawn_unit(data.world, self.wave, self.index_spawn);
                 self.index_spawn += 1;
                 self.last_spawn_time = current_time;
             }
+            self.show_wave(data.world);
+            return Trans::None;
+        }
+
+        // Wave is cleared once every spawned unit died or reached the goal
+        if PlayState::enemies_left(data.world) {
+            self.show_wave(data.world);
+            return Trans::None;
+        }
+        pay_wave_income(data.world);
+
+        // Endless mode generates waves after the authored ones
+        if !ensure_wave(data.world, self.wave + 1) {
+            return self.finish_level(data.world, true);
         }
+        self.wave += 1;
+        self.index_spawn = 0;
+        self.last_spawn_time = current_time + data.world.fetch::<LevelInfo>().wave_delay as u128;
+        data.world.write_resource::<TransactionLog>().wave = self.wave;
+        self.show_wave(data.world);
         Trans::None
     }
 }
@@ -0,0 +1,182 @@
+extern crate amethyst;
+use amethyst::prelude::*;
+use amethyst::{
+    assets::Loader,
+    ecs::Entity,
+    input::{is_close_requested, InputEvent},
+    ui::{Anchor, Interactable, TtfFormat, UiEventType, UiText, UiTransform},
+};
+use crate::game_speed::{GameSpeed, GAME_SPEEDS};
+use crate::settings::{save_settings, Settings, CAMERA_SPEEDS, RESOLUTIONS};
+use crate::locale::{available_languages, set_language, Locale};
+use crate::difficulty::update_difficulty_text;
+use crate::endless::update_endless_text;
+
+const OPTIONS: [&str; 11] = [
+    "master_volume", "effects_volume", "ui_volume", "music_volume",
+    "window_mode", "resolution", "camera_speed", "edge_scrolling",
+    "health_bars", "language", "game_speed",
+];
+
+// Value after `current` in the list, the first one when `current` isn't listed
+fn next_in<T: Clone + PartialEq>(values: &[T], current: &T) -> T {
+    let next = values.iter().position(|x| x == current).map_or(0, |i| (i + 1) % values.len());
+    values[next].clone()
+}
+
+// Volumes go up in steps of 0.1 and wrap to 0.0 after 1.0
+fn next_volume(volume: f32) -> f32 {
+    (((volume * 10.0).round() as u32 + 1) % 11) as f32 / 10.0
+}
+
+fn on_off(locale: &Locale, value: bool) -> String {
+    locale.tr(if value { "settings.on" } else { "settings.off" })
+}
+
+fn option_text(locale: &Locale, settings: &Settings, option: &str) -> String {
+    let value = match option {
+        "master_volume" => format!("{:.0}%", settings.volumes.master * 100.0),
+        "effects_volume" => format!("{:.0}%", settings.volumes.effects * 100.0),
+        "ui_volume" => format!("{:.0}%", settings.volumes.ui * 100.0),
+        "music_volume" => format!("{:.0}%", settings.volumes.music * 100.0),
+        "window_mode" => locale.data_name("window_mode", &format!("{:?}", settings.window_mode).to_lowercase()),
+        "resolution" => settings.resolution.map_or(locale.tr("settings.default"), |(w, h)| format!("{}x{}", w, h)),
+        "camera_speed" => format!("{}", settings.camera_speed),
+        "edge_scrolling" => on_off(locale, settings.edge_scrolling),
+        "health_bars" => on_off(locale, settings.health_bars),
+        "language" => locale.name().to_string(),
+        "game_speed" => format!("x{}", settings.game_speed),
+        _ => "".to_string(),
+    };
+    locale.tr_with("settings.option", &[("option", locale.tr(&format!("settings.{}", option))), ("value", value)])
+}
+
+fn change_language(world: &mut World) {
+    let languages = available_languages();
+    if languages.is_empty() {
+        return;
+    }
+    let language = next_in(&languages, &world.fetch::<Settings>().language);
+    set_language(world, &language);
+    update_difficulty_text(world);
+    update_endless_text(world);
+    save_settings(&world.fetch::<Settings>());
+}
+
+fn change_option(world: &mut World, option: &str) {
+    if option == "language" {
+        change_language(world);
+        return;
+    }
+
+    let mut settings = world.write_resource::<Settings>();
+    match option {
+        "master_volume" => settings.volumes.master = next_volume(settings.volumes.master),
+        "effects_volume" => settings.volumes.effects = next_volume(settings.volumes.effects),
+        "ui_volume" => settings.volumes.ui = next_volume(settings.volumes.ui),
+        "music_volume" => settings.volumes.music = next_volume(settings.volumes.music),
+        "window_mode" => settings.window_mode = settings.window_mode.next(),
+        "resolution" => {
+            let resolutions = RESOLUTIONS.iter().map(|x| Some(*x)).collect::<Vec<_>>();
+            settings.resolution = next_in(&resolutions, &settings.resolution);
+        },
+        "camera_speed" => settings.camera_speed = next_in(&CAMERA_SPEEDS, &settings.camera_speed),
+        "edge_scrolling" => settings.edge_scrolling = !settings.edge_scrolling,
+        "health_bars" => settings.health_bars = !settings.health_bars,
+        "game_speed" => {
+            settings.game_speed = next_in(&GAME_SPEEDS, &settings.game_speed);
+            world.write_resource::<GameSpeed>().set(settings.game_speed);
+        },
+        _ => return,
+    }
+    save_settings(&settings);
+}
+
+// Clicking an option switches it to the next value. Systems read `Settings`
+// every frame, so changes are applied at once and saved to config/settings.ron
+#[derive(Default)]
+pub struct SettingsState {
+    // Option name and its label
+    labels: Vec<(&'static str, Entity)>,
+    title: Option<Entity>,
+}
+
+impl SettingsState {
+    fn refresh(&self, world: &World) {
+        let settings = world.read_resource::<Settings>();
+        let locale = world.read_resource::<Locale>();
+        let mut ui_texts = world.write_storage::<UiText>();
+        for (option, entity) in self.labels.iter() {
+            if let Some(ui_text) = ui_texts.get_mut(*entity) {
+                ui_text.text = option_text(&locale, &settings, option);
+            }
+        }
+    }
+}
+
+impl SimpleState for SettingsState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let font = world.read_resource::<Loader>().load(
+            "font/square.ttf", TtfFormat, (), &world.read_resource(),
+        );
+
+        let title = world
+            .create_entity()
+            .with(UiText::new(font.clone(), "@settings.title".to_string(), [1.0, 1.0, 0.0, 1.0], 25.0))
+            .with(UiTransform::new(
+                "settings-title".to_string(), Anchor::TopMiddle, Anchor::Middle,
+                0.0, -40.0, 300.0, 500.0, 30.0,
+            ))
+            .build();
+        self.title = Some(title);
+
+        for (i, option) in OPTIONS.iter().enumerate() {
+            let entity = world
+                .create_entity()
+                .with(UiText::new(font.clone(), "".to_string(), [1.0, 1.0, 1.0, 1.0], 18.0))
+                .with(UiTransform::new(
+                    format!("settings-{}", option), Anchor::TopMiddle, Anchor::Middle,
+                    0.0, -80.0 - i as f32 * 24.0, 300.0, 500.0, 24.0,
+                ))
+                .with(Interactable)
+                .build();
+            self.labels.push((*option, entity));
+        }
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let entities = self.labels.drain(..).map(|(_, e)| e).chain(self.title.take());
+        data.world.delete_entities(&entities.collect::<Vec<_>>()).unwrap();
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        match &event {
+            StateEvent::Window(event) => {
+                if is_close_requested(&event) {
+                    return Trans::Quit;
+                }
+            },
+            StateEvent::Input(InputEvent::ActionPressed(action)) => {
+                if action == "quit" || action == "settings_menu" {
+                    return Trans::Pop;
+                }
+            },
+            StateEvent::Ui(ui_event) if ui_event.event_type == UiEventType::Click => {
+                let clicked = self.labels.iter().find(|(_, e)| *e == ui_event.target).map(|(x, _)| *x);
+                if let Some(option) = clicked {
+                    change_option(data.world, option);
+                    self.refresh(data.world);
+                }
+            },
+            _ => (),
+        }
+
+        Trans::None
+    }
+}
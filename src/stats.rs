@@ -0,0 +1,121 @@
+use amethyst::{
+    prelude::*,
+    utils::application_root_dir,
+};
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use crate::difficulty::{Difficulty, CUSTOM_DIFFICULTY};
+use crate::economy::TransactionLog;
+use crate::endless::EndlessMode;
+use crate::map::LevelInfo;
+use crate::UserData;
+
+// Choices kept between games in config/save.ron
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SaveData {
+    // Last chosen difficulty preset, the one selected in assets/difficulty.json when empty
+    pub difficulty: String,
+    // Multipliers of the "custom" difficulty preset
+    pub custom_difficulty: Difficulty,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData {
+            difficulty: "".to_string(),
+            custom_difficulty: Difficulty {
+                name: CUSTOM_DIFFICULTY.to_string(),
+                ..Difficulty::default()
+            },
+        }
+    }
+}
+
+// One finished run of a level
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunRecord {
+    pub level: String,
+    pub difficulty: String,
+    pub endless: bool,
+    pub won: bool,
+    // Waves reached, from 1
+    pub waves: u32,
+    pub lives: u32,
+    // Money by source
+    pub money: Vec<(String, f32)>,
+}
+
+fn config_path(file: &str) -> Option<PathBuf> {
+    match application_root_dir() {
+        Ok(e) => Some(e.join("config").join(file)),
+        Err(e) => {
+            println!("Can't find config directory: {}", e);
+            None
+        }
+    }
+}
+
+// None when the file is missing or broken
+fn read_ron<T: DeserializeOwned>(file: &str) -> Option<T> {
+    let path = config_path(file)?;
+    let data = fs::read_to_string(&path).ok()?;
+    match ron::de::from_str::<T>(&data) {
+        Ok(e) => Some(e),
+        Err(e) => {
+            println!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn write_ron<T: Serialize>(file: &str, value: &T) {
+    let path = match config_path(file) {
+        Some(e) => e,
+        None => return,
+    };
+    let data = match to_string_pretty(value, PrettyConfig::default()) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't serialize {}: {}", file, e);
+            return;
+        }
+    };
+    if let Err(e) = fs::write(&path, data) {
+        println!("Can't write {}: {}", path.display(), e);
+    }
+}
+
+// Defaults are used until the game is saved for the first time
+pub fn load_save() -> SaveData {
+    read_ron::<SaveData>("save.ron").unwrap_or_default()
+}
+
+pub fn write_save(save: &SaveData) {
+    write_ron("save.ron", save);
+}
+
+// Runs in config/stats.ron, oldest first
+pub fn load_stats() -> Vec<RunRecord> {
+    read_ron::<Vec<RunRecord>>("stats.ron").unwrap_or_default()
+}
+
+// Adds the run which just finished to config/stats.ron
+pub fn record_run(world: &World, won: bool) {
+    let log = world.fetch::<TransactionLog>();
+    let record = RunRecord {
+        level: world.fetch::<LevelInfo>().name.clone(),
+        difficulty: world.fetch::<Difficulty>().name.clone(),
+        endless: world.fetch::<EndlessMode>().enabled,
+        won: won,
+        waves: log.wave + 1,
+        lives: world.fetch::<UserData>().lives,
+        money: log.totals().iter().map(|(reason, amount)| (reason.name().to_string(), *amount)).collect(),
+    };
+
+    let mut stats = load_stats();
+    stats.push(record);
+    write_ron("stats.ron", &stats);
+}
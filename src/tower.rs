@@ -0,0 +1,381 @@
+use amethyst::{
+    prelude::*,
+    core::{transform::Transform, Named, Time},
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Entity, Join, LazyUpdate, Read, ReadStorage,
+        System, Write, WriteExpect, WriteStorage,
+    },
+    shrev::EventChannel,
+    renderer::SpriteRender,
+    core::math::{Point3, Vector3},
+    input::{InputHandler, StringBindings},
+};
+use amethyst_tiles::{MortonEncoder2D, TileMap, Map};
+use serde::{Deserialize, Serialize};
+use crate::camera::CursorPosition;
+use crate::effect::{EffectTypes, StatusEffects};
+use crate::damage::{calculate_damage, DamageType};
+use crate::animation::{spawn_one_shot, AnimationClip};
+use crate::particle::ParticleEvent;
+use crate::audio::SoundEvent;
+use crate::map::{BlockTile, LevelInfo};
+use crate::unit::{Locomotion, SpriteData, Unit};
+use crate::selection::Selection;
+use crate::spatial::SpatialIndex;
+use crate::economy::{debit, Reason, TransactionLog};
+use crate::UserData;
+
+// Team of the player, units of any other team are enemies for towers
+pub const PLAYER_TEAM: u32 = 0;
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct TowerType {
+    pub name: String,
+    pub sprite_name: String,
+    pub sprite_id: u32,
+    pub cost: f32,
+    pub damage: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub range: f32,
+    // Seconds between two shots
+    pub cooldown: f32,
+    // Unit locomotion kinds this tower can hit
+    pub targets: Vec<Locomotion>,
+    // Status effects applied to the target on every hit
+    #[serde(default)]
+    pub effects: Vec<String>,
+    // First tier of the upgrade tree, every entry is a branch
+    #[serde(default)]
+    pub upgrades: Vec<TowerUpgrade>,
+    // One shot animation played on the target for every hit
+    #[serde(default)]
+    pub hit_effect: Option<AnimationClip>,
+}
+
+// Node of a tower upgrade tree, stat values are added to the tower stats
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct TowerUpgrade {
+    pub name: String,
+    pub cost: f32,
+    #[serde(default)]
+    pub damage: f32,
+    #[serde(default)]
+    pub range: f32,
+    #[serde(default)]
+    pub cooldown: f32,
+    #[serde(default)]
+    pub effects: Vec<String>,
+    #[serde(default)]
+    pub sprite_id: Option<u32>,
+    // Next tier
+    #[serde(default)]
+    pub upgrades: Vec<TowerUpgrade>,
+}
+
+fn default_sell_refund() -> f32 {
+    0.7
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct TowerTypes {
+    pub types: Vec<TowerType>,
+    // Part of the total spend on a tower returned when it is sold
+    #[serde(default = "default_sell_refund")]
+    pub sell_refund: f32,
+}
+
+// Tower type chosen with a hotkey, built on the next click on an empty tile while `active`
+#[derive(Default)]
+pub struct TowerBuild {
+    pub selected: usize,
+    pub active: bool,
+}
+
+#[derive(Clone)]
+pub struct Tower {
+    pub tower_type: TowerType,
+    pub tile: Point3<u32>,
+    pub cooldown: f32,
+    // Upgrades which can be bought next
+    pub upgrades: Vec<TowerUpgrade>,
+    // Money spent on building and upgrading
+    pub spent: f32,
+    pub kills: u32,
+    // Direct hit damage after armour and resistances
+    pub damage_dealt: f32,
+}
+
+impl Component for Tower {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Tower {
+    pub fn new(tower_type: TowerType, tile: Point3<u32>) -> Tower {
+        Tower {
+            upgrades: tower_type.upgrades.clone(),
+            spent: tower_type.cost,
+            cooldown: 0.0,
+            kills: 0,
+            damage_dealt: 0.0,
+            tile: tile,
+            tower_type: tower_type,
+        }
+    }
+
+    // Applies upgrade stats and moves to the next tier of the tree
+    pub fn upgrade(&mut self, index: usize) -> Option<TowerUpgrade> {
+        let upgrade = self.upgrades.get(index)?.clone();
+
+        self.tower_type.damage += upgrade.damage;
+        self.tower_type.range += upgrade.range;
+        self.tower_type.cooldown = (self.tower_type.cooldown + upgrade.cooldown).max(0.05);
+        for effect in &upgrade.effects {
+            if !self.tower_type.effects.contains(effect) {
+                self.tower_type.effects.push(effect.clone());
+            }
+        }
+        if let Some(sprite_id) = upgrade.sprite_id {
+            self.tower_type.sprite_id = sprite_id;
+        }
+
+        self.spent += upgrade.cost;
+        self.upgrades = upgrade.upgrades.clone();
+        Some(upgrade)
+    }
+
+    pub fn sell_price(&self, sell_refund: f32) -> f32 {
+        self.spent * sell_refund
+    }
+}
+
+pub fn load_tower_info(world: &mut World) {
+    world.register::<Tower>();
+    world.insert::<TowerBuild>(TowerBuild::default());
+}
+
+#[derive(Default)]
+pub struct TowerPlacementSystem {
+    mouse_down: bool,
+}
+
+impl<'s> System<'s> for TowerPlacementSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Tower>,
+        ReadStorage<'s, Unit>,
+        ReadStorage<'s, TileMap<BlockTile, MortonEncoder2D>>,
+        Read<'s, TowerTypes>,
+        Read<'s, SpriteData>,
+        Read<'s, LevelInfo>,
+        Read<'s, CursorPosition>,
+        Read<'s, InputHandler<StringBindings>>,
+        Write<'s, TowerBuild>,
+        Write<'s, Selection>,
+        WriteExpect<'s, UserData>,
+        Write<'s, TransactionLog>,
+        Read<'s, Time>,
+        Read<'s, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            towers,
+            units,
+            tilemaps,
+            tower_types,
+            sprite_data,
+            level_info,
+            cursor,
+            input,
+            mut tower_build,
+            mut selection,
+            mut user_data,
+            mut log,
+            time,
+            lazy_update,
+        ): Self::SystemData,
+    ) {
+        // Tower hotkeys are `tower_1` .. `tower_9` actions
+        for i in 0..tower_types.types.len().min(9) {
+            if input.action_is_down(&format!("tower_{}", i + 1)).unwrap_or(false) {
+                tower_build.selected = i;
+                tower_build.active = true;
+            }
+        }
+
+        // Build only once per click
+        let mouse_down = input.action_is_down("select").unwrap_or(false);
+        let clicked = mouse_down && !self.mouse_down;
+        self.mouse_down = mouse_down;
+        if !clicked {
+            return;
+        }
+
+        // Click on a unit or a tower selects it
+        let hovered = cursor.hovered.filter(|e| units.contains(*e) || towers.contains(*e));
+        if hovered.is_some() {
+            selection.entity = hovered;
+            tower_build.active = false;
+            return;
+        }
+
+        let tile = match cursor.tile {
+            Some(e) => e,
+            None => return,
+        };
+
+        selection.entity = (&entities, &towers).join()
+            .find(|(_, tower)| tower.tile.x == tile.x && tower.tile.y == tile.y)
+            .map(|(entity, _)| entity);
+        if selection.entity.is_some() {
+            tower_build.active = false;
+            return;
+        }
+
+        // Click on empty ground outside build mode only deselects
+        if !tower_build.active {
+            return;
+        }
+
+        let buildable = if level_info.buildable.is_empty() {
+            !level_info.is_on_path(tile.x, tile.y)
+        } else {
+            level_info.buildable.contains(&vec![tile.x, tile.y])
+        };
+        if !buildable {
+            return;
+        }
+
+        let tower_type = match tower_types.types.get(tower_build.selected) {
+            Some(e) => e.clone(),
+            None => return,
+        };
+        let tile_map = match (&tilemaps).join().next() {
+            Some(e) => e,
+            None => return,
+        };
+        let pos = tile_map.to_world(&tile, None);
+
+        if !debit(&mut user_data, &mut log, &time, tower_type.cost, Reason::Build) {
+            return;
+        }
+        tower_build.active = false;
+
+        let sprite_render = SpriteRender {
+            sprite_sheet: sprite_data.sprite_handles[&tower_type.sprite_name].clone(),
+            sprite_number: tower_type.sprite_id as usize,
+        };
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(pos.x, pos.y, 0.0);
+
+        lazy_update
+            .create_entity(&entities)
+            .with(sprite_render)
+            .with(transform)
+            .with(Named::new(tower_type.name.clone()))
+            .with(Tower::new(tower_type, tile))
+            .build();
+    }
+}
+
+#[derive(Default)]
+pub struct TowerSystem;
+
+impl<'s> System<'s> for TowerSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Tower>,
+        WriteStorage<'s, Unit>,
+        WriteStorage<'s, StatusEffects>,
+        ReadStorage<'s, Transform>,
+        Read<'s, EffectTypes>,
+        Read<'s, SpriteData>,
+        Read<'s, LazyUpdate>,
+        Write<'s, EventChannel<ParticleEvent>>,
+        Write<'s, EventChannel<SoundEvent>>,
+        Read<'s, Time>,
+        Read<'s, SpatialIndex>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut towers,
+            mut units,
+            mut status_effects,
+            transforms,
+            effect_types,
+            sprite_data,
+            lazy_update,
+            mut particle_events,
+            mut sound_events,
+            time,
+            spatial_index,
+        ): Self::SystemData,
+    ) {
+        for (tower, tower_transform) in (&mut towers, &transforms).join() {
+            tower.cooldown -= time.delta_seconds();
+            if tower.cooldown > 0.0 {
+                continue;
+            }
+
+            // Find the nearest enemy this tower is able to hit
+            let tower_pos = tower_transform.translation();
+            let target = spatial_index.query_radius(&entities, tower_pos.x, tower_pos.y, tower.tower_type.range)
+                .into_iter()
+                .filter_map(|entity| Some((entity, units.get(entity)?, transforms.get(entity)?)))
+                .filter(|(_, unit, _)| {
+                    unit.team != PLAYER_TEAM
+                        && unit.health > 0.0
+                        && !unit.attacking
+                        && tower.tower_type.targets.contains(&unit.unit_type.locomotion)
+                })
+                .map(|(entity, _, transform)| {
+                    let offset = transform.translation() - tower_pos;
+                    (entity, Vector3::new(offset.x, offset.y, 0.0).norm())
+                })
+                .filter(|(_, distance)| *distance <= tower.tower_type.range)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+            if let Some((entity, _)) = target {
+                let mut damage_taken_multiplier = 1.0;
+                if let Some(effects) = status_effects.get_mut(entity) {
+                    damage_taken_multiplier = effects.damage_taken_multiplier();
+                    for name in &tower.tower_type.effects {
+                        if let Some(effect_type) = effect_types.get(name) {
+                            effects.apply(effect_type);
+                        }
+                    }
+                }
+
+                let unit = units.get_mut(entity).unwrap();
+                let damage = calculate_damage(
+                    tower.tower_type.damage, tower.tower_type.damage_type, &unit.unit_type, damage_taken_multiplier,
+                );
+                unit.take_damage(damage);
+                tower.damage_dealt += damage;
+                if unit.health <= 0.0 {
+                    tower.kills += 1;
+                }
+
+                let transform = transforms.get(entity).unwrap();
+                if let Some(clip) = &tower.tower_type.hit_effect {
+                    let mut effect_transform = transform.clone();
+                    effect_transform.set_translation_z(0.5);
+                    spawn_one_shot(&lazy_update, &entities, &sprite_data, clip, &tower.tower_type.sprite_name, effect_transform);
+                }
+                particle_events.single_write(ParticleEvent {
+                    emitter: "impact".to_string(),
+                    pos: *transform.translation(),
+                });
+                sound_events.single_write(SoundEvent::TowerFire);
+                sound_events.single_write(SoundEvent::Hit);
+                tower.cooldown = tower.tower_type.cooldown;
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use amethyst::{
+    assets::AssetStorage,
+    core::{math::Point3, Hidden, Transform},
+    ecs::{Join, Read, ReadStorage, System, Write},
+    renderer::{debug_drawing::DebugLines, SpriteRender, SpriteSheet},
+};
+use amethyst_rendy::palette::Srgba;
+use crate::settings::Settings;
+use crate::unit::Unit;
+use crate::utils::sprite_size;
+
+// World units between the sprite top and the bar
+const BAR_OFFSET: f32 = 3.0;
+// Lines drawn on top of each other, debug lines are one pixel wide
+const BAR_THICKNESS: u32 = 2;
+
+// Draws a bar over damaged units, shown when `Settings::health_bars` is on
+#[derive(Default)]
+pub struct HealthBarSystem;
+
+impl<'s> System<'s> for HealthBarSystem {
+    type SystemData = (
+        ReadStorage<'s, Unit>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, SpriteRender>,
+        ReadStorage<'s, Hidden>,
+        Read<'s, AssetStorage<SpriteSheet>>,
+        Read<'s, Settings>,
+        Write<'s, DebugLines>,
+    );
+
+    fn run(&mut self, (units, transforms, sprites, hiddens, sprite_sheets, settings, mut debug_lines): Self::SystemData) {
+        if !settings.health_bars {
+            return;
+        }
+
+        for (unit, transform, sprite, _) in (&units, &transforms, &sprites, !&hiddens).join() {
+            if unit.health >= unit.unit_type.max_health || unit.health <= 0.0 {
+                continue;
+            }
+            let (width, height) = match sprite_size(&sprite_sheets, sprite) {
+                Some(e) => e,
+                None => continue,
+            };
+
+            let pos = transform.translation();
+            let left = pos.x - width * 0.5;
+            let filled = width * (unit.health / unit.unit_type.max_health).min(1.0);
+            for i in 0..BAR_THICKNESS {
+                let y = pos.y + height * 0.5 + BAR_OFFSET + i as f32;
+                debug_lines.draw_line(
+                    Point3::new(left, y, pos.z + 0.1),
+                    Point3::new(left + width, y, pos.z + 0.1),
+                    Srgba::new(0.6, 0.0, 0.0, 1.0),
+                );
+                debug_lines.draw_line(
+                    Point3::new(left, y, pos.z + 0.2),
+                    Point3::new(left + filled, y, pos.z + 0.2),
+                    Srgba::new(0.0, 0.9, 0.0, 1.0),
+                );
+            }
+        }
+    }
+}
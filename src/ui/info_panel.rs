@@ -0,0 +1,139 @@
+use amethyst::{
+    ui::{UiText, Anchor, UiTransform, TtfFormat, Interactable, UiFinder, FontHandle},
+    prelude::{World, WorldExt, Builder},
+    assets::Loader,
+    ecs::{ReadStorage, Read, System, WriteStorage},
+};
+use crate::effect::StatusEffects;
+use crate::selection::Selection;
+use crate::tower::Tower;
+use crate::unit::Unit;
+use crate::locale::Locale;
+
+const INFO_LINES: usize = 5;
+
+fn create_label(world: &mut World, font: FontHandle, id: String, y: f32, size: f32) {
+    let ui_text = UiText::new(font, "".to_string(), [1.0, 1.0, 1.0, 1.0], size);
+    let ui_transform = UiTransform::new(
+        id,
+        Anchor::TopLeft,
+        Anchor::Middle,
+        170.0, y, 200.0, 320.0, 25.0,
+    );
+
+    world
+        .create_entity()
+        .with(ui_text)
+        .with(ui_transform)
+        .with(Interactable)
+        .build();
+}
+
+pub fn create_info_panel(world: &mut World) {
+    let font = world.read_resource::<Loader>().load(
+        "font/square.ttf", TtfFormat, (), &world.read_resource(),
+    );
+
+    create_label(world, font.clone(), "info-title".to_string(), -70.0, 22.0);
+    for i in 0..INFO_LINES {
+        create_label(world, font.clone(), format!("info-line-{}", i), -95.0 - i as f32 * 22.0, 16.0);
+    }
+}
+
+fn unit_lines(locale: &Locale, unit: &Unit, effects: Option<&StatusEffects>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut health = locale.tr_with("info.health", &[
+        ("health", format!("{:.0}", unit.health.max(0.0))),
+        ("max_health", format!("{:.0}", unit.unit_type.max_health)),
+    ]);
+    if unit.shield > 0.0 {
+        health += &locale.tr_with("info.shield", &[("shield", format!("{:.0}", unit.shield))]);
+    }
+    lines.push(health);
+    lines.push(locale.tr_with("info.speed", &[
+        ("speed", unit.unit_type.speed.to_string()),
+        ("armour", unit.unit_type.armour.to_string()),
+    ]));
+    lines.push(locale.data_name("locomotion", &format!("{:?}", unit.unit_type.locomotion).to_lowercase()));
+
+    let active = effects.map_or(Vec::new(), |x| {
+        x.active.iter()
+            .map(|e| {
+                let args = [
+                    ("name", locale.data_name("effect", &e.effect_type.name)),
+                    ("time", format!("{:.1}", e.remaining)),
+                    ("stacks", e.stacks.to_string()),
+                ];
+                match e.stacks {
+                    1 => locale.tr_with("info.effect", &args),
+                    _ => locale.tr_with("info.effect_stacks", &args),
+                }
+            })
+            .collect()
+    });
+    if !active.is_empty() {
+        lines.push(active.join(", "));
+    }
+    lines
+}
+
+fn tower_lines(locale: &Locale, tower: &Tower) -> Vec<String> {
+    let tower_type = &tower.tower_type;
+    let damage_type = format!("{:?}", tower_type.damage_type).to_lowercase();
+    let mut lines = vec![
+        locale.tr_with("info.damage", &[
+            ("damage", tower_type.damage.to_string()),
+            ("type", locale.data_name("damage_type", &damage_type)),
+        ]),
+        locale.tr_with("info.range", &[
+            ("range", tower_type.range.to_string()),
+            ("cooldown", format!("{:.2}", tower_type.cooldown)),
+        ]),
+        locale.tr_count("info.kills", tower.kills as i64, &[("damage", format!("{:.0}", tower.damage_dealt))]),
+    ];
+    if !tower_type.effects.is_empty() {
+        let effects = tower_type.effects.iter().map(|x| locale.data_name("effect", x)).collect::<Vec<_>>();
+        lines.push(locale.tr_with("info.applies", &[("effects", effects.join(", "))]));
+    }
+    lines
+}
+
+// Shows stats of the selected unit or tower
+#[derive(Default)]
+pub struct InfoPanelSystem;
+
+impl<'s> System<'s> for InfoPanelSystem {
+    type SystemData = (
+        ReadStorage<'s, Unit>,
+        ReadStorage<'s, Tower>,
+        ReadStorage<'s, StatusEffects>,
+        WriteStorage<'s, UiText>,
+        UiFinder<'s>,
+        Read<'s, Selection>,
+        Read<'s, Locale>,
+    );
+
+    fn run(&mut self, (units, towers, status_effects, mut ui_texts, ui_finder, selection, locale): Self::SystemData) {
+        let (title, lines) = match selection.entity {
+            Some(entity) => {
+                if let Some(unit) = units.get(entity) {
+                    (locale.data_name("unit", &unit.unit_type.name), unit_lines(&locale, unit, status_effects.get(entity)))
+                } else if let Some(tower) = towers.get(entity) {
+                    (locale.data_name("tower", &tower.tower_type.name), tower_lines(&locale, tower))
+                } else {
+                    ("".to_string(), Vec::new())
+                }
+            },
+            None => ("".to_string(), Vec::new()),
+        };
+
+        if let Some(ui_text) = ui_finder.find("info-title").and_then(|e| ui_texts.get_mut(e)) {
+            ui_text.text = title;
+        }
+        for i in 0..INFO_LINES {
+            if let Some(ui_text) = ui_finder.find(&format!("info-line-{}", i)).and_then(|e| ui_texts.get_mut(e)) {
+                ui_text.text = lines.get(i).cloned().unwrap_or_default();
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use amethyst::{
-    ui::{UiText, Anchor, UiTransform, TtfFormat},
+    ui::{UiText, Anchor, UiTransform, TtfFormat, Interactable, FontHandle},
     prelude::{World, WorldExt, Builder},
+    ecs::Entity,
     assets::Loader,
 };
 
@@ -9,7 +10,7 @@ pub fn create_menu(world: &mut World) {
         "font/square.ttf", TtfFormat, (), &world.read_resource(),
     );
 
-    let ui_text = UiText::new(font, "123".to_string(), [1.0, 1.0, 1.0, 1.0], 25.0);
+    let ui_text = UiText::new(font.clone(), "123".to_string(), [1.0, 1.0, 1.0, 1.0], 25.0);
     let ui_transform = UiTransform::new(
         "activate".to_string(),
         Anchor::TopRight,
@@ -22,4 +23,30 @@ pub fn create_menu(world: &mut World) {
         .with(ui_text)
         .with(ui_transform)
         .build();
+
+    // Clicking cycles difficulty presets, text is set by `initialise_difficulty`
+    create_option(world, font.clone(), "difficulty".to_string(), -60.0);
+    // Clicking toggles endless mode, text is set by `initialise_endless`
+    create_option(world, font.clone(), "endless".to_string(), -90.0);
+    let settings = create_option(world, font, "settings".to_string(), -120.0);
+    if let Some(ui_text) = world.write_storage::<UiText>().get_mut(settings) {
+        ui_text.text = "@menu.settings".to_string();
+    }
+}
+
+fn create_option(world: &mut World, font: FontHandle, id: String, y: f32) -> Entity {
+    let ui_text = UiText::new(font, "".to_string(), [1.0, 1.0, 1.0, 1.0], 20.0);
+    let ui_transform = UiTransform::new(
+        id,
+        Anchor::TopMiddle,
+        Anchor::Middle,
+        0.0, y, 200.0, 350.0, 30.0,
+    );
+
+    world
+        .create_entity()
+        .with(ui_text)
+        .with(ui_transform)
+        .with(Interactable)
+        .build()
 }
@@ -0,0 +1,298 @@
+use amethyst::{
+    ui::{Anchor, Interactable, UiFinder, UiImage, UiTransform},
+    prelude::{World, WorldExt, Builder},
+    ecs::{
+        Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, Write, WriteStorage,
+    },
+    core::{Hidden, Transform},
+    input::{InputHandler, StringBindings},
+    renderer::camera::{ActiveCamera, Camera},
+    window::ScreenDimensions,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::camera::{clamp_to_map, world_per_pixel};
+use crate::camera_director::CameraDirector;
+use crate::map::MapData;
+use crate::tower::{Tower, PLAYER_TEAM};
+use crate::unit::Unit;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct MinimapConfig {
+    // Minimap side in pixels
+    pub size: f32,
+    // Distance from the bottom left window corner in pixels
+    pub margin: f32,
+    // Map tiles per minimap cell on each axis
+    pub cell_tiles: u32,
+    // Colours by tile id as it is stored in the Tiled json, upper layers win
+    pub tile_colours: HashMap<u32, [f32; 4]>,
+    pub default_colour: [f32; 4],
+    pub enemy_colour: [f32; 4],
+    pub tower_colour: [f32; 4],
+    pub viewport_colour: [f32; 4],
+}
+
+fn create_rect(world: &mut World, id: String, x: f32, y: f32, z: f32, size: (f32, f32), colour: [f32; 4]) -> Entity {
+    let ui_transform = UiTransform::new(
+        id,
+        Anchor::BottomLeft,
+        Anchor::BottomLeft,
+        x, y, z, size.0, size.1,
+    );
+
+    world
+        .create_entity()
+        .with(ui_transform)
+        .with(UiImage::SolidColor(colour))
+        .build()
+}
+
+fn cell_colour(map_data: &MapData, config: &MinimapConfig, x: u32, y: u32) -> [f32; 4] {
+    for layer in map_data.layers.iter().rev() {
+        let id = layer.data[map_data.x_y_to_index(x, y)];
+        if let Some(colour) = config.tile_colours.get(&id) {
+            return *colour;
+        }
+    }
+    config.default_colour
+}
+
+pub fn create_minimap(world: &mut World) {
+    let root = {
+        let config = world.fetch::<MinimapConfig>();
+        (config.margin, config.size)
+    };
+    let (margin, size) = root;
+
+    // Background also blocks clicks to the map under the minimap
+    let background = create_rect(
+        world, "minimap".to_string(), margin, margin, 100.0, (size, size), [0.0, 0.0, 0.0, 1.0],
+    );
+    world.write_storage::<Interactable>().insert(background, Interactable).unwrap();
+
+    let mut cells = Vec::<(u32, u32, [f32; 4])>::new();
+    let (columns, rows) = {
+        let map_data = world.fetch::<MapData>();
+        let config = world.fetch::<MinimapConfig>();
+        let step = config.cell_tiles.max(1);
+        let columns = (map_data.width + step - 1) / step;
+        let rows = (map_data.height + step - 1) / step;
+        for row in 0..rows {
+            for column in 0..columns {
+                cells.push((column, row, cell_colour(&map_data, &config, column * step, row * step)));
+            }
+        }
+        (columns, rows)
+    };
+
+    let cell_width = size / columns as f32;
+    let cell_height = size / rows as f32;
+    for (column, row, colour) in cells {
+        // Map row 0 is at the top
+        create_rect(
+            world,
+            format!("minimap-cell-{}-{}", column, row),
+            margin + column as f32 * cell_width,
+            margin + (rows - 1 - row) as f32 * cell_height,
+            101.0,
+            (cell_width, cell_height),
+            colour,
+        );
+    }
+}
+
+// Recolours the minimap cell of a tile changed after the minimap was built
+pub fn refresh_minimap_cell(world: &mut World, x: u32, y: u32) {
+    let (id, colour) = {
+        let map_data = world.fetch::<MapData>();
+        let config = world.fetch::<MinimapConfig>();
+        let step = config.cell_tiles.max(1);
+        let (column, row) = (x / step, y / step);
+        (format!("minimap-cell-{}-{}", column, row), cell_colour(&map_data, &config, column * step, row * step))
+    };
+    let entity = world.exec(|ui_finder: UiFinder<'_>| ui_finder.find(&id));
+    let mut ui_images = world.write_storage::<UiImage>();
+    if let Some(ui_image) = entity.and_then(|e| ui_images.get_mut(e)) {
+        *ui_image = UiImage::SolidColor(colour);
+    }
+}
+
+// Moves minimap markers and the viewport frame, moves the camera on click
+#[derive(Default)]
+pub struct MinimapSystem {
+    unit_dots: Vec<Entity>,
+    tower_dots: Vec<Entity>,
+    // Top, bottom, left and right border of the camera view
+    viewport: Vec<Entity>,
+}
+
+impl MinimapSystem {
+    fn place_dots(
+        pool: &mut Vec<Entity>,
+        positions: &Vec<(f32, f32)>,
+        (dot_size, colour): (f32, [f32; 4]),
+        entities: &Entities<'_>,
+        ui_transforms: &mut WriteStorage<'_, UiTransform>,
+        ui_images: &mut WriteStorage<'_, UiImage>,
+        hiddens: &mut WriteStorage<'_, Hidden>,
+    ) {
+        while pool.len() < positions.len() {
+            let entity = entities.create();
+            ui_transforms.insert(entity, UiTransform::new(
+                "minimap-dot".to_string(), Anchor::BottomLeft, Anchor::Middle,
+                0.0, 0.0, 102.0, dot_size, dot_size,
+            )).unwrap();
+            ui_images.insert(entity, UiImage::SolidColor(colour)).unwrap();
+            pool.push(entity);
+        }
+
+        for (i, entity) in pool.iter().enumerate() {
+            match positions.get(i) {
+                Some((x, y)) => {
+                    hiddens.remove(*entity);
+                    if let Some(ui_transform) = ui_transforms.get_mut(*entity) {
+                        ui_transform.local_x = *x;
+                        ui_transform.local_y = *y;
+                    }
+                },
+                None => {
+                    hiddens.insert(*entity, Hidden).unwrap();
+                },
+            }
+        }
+    }
+}
+
+impl<'s> System<'s> for MinimapSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Unit>,
+        ReadStorage<'s, Tower>,
+        ReadStorage<'s, Camera>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, UiTransform>,
+        WriteStorage<'s, UiImage>,
+        WriteStorage<'s, Hidden>,
+        Read<'s, ActiveCamera>,
+        Read<'s, MapData>,
+        Read<'s, MinimapConfig>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, InputHandler<StringBindings>>,
+        Write<'s, CameraDirector>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            units,
+            towers,
+            cameras,
+            mut transforms,
+            mut ui_transforms,
+            mut ui_images,
+            mut hiddens,
+            active_camera,
+            map_data,
+            config,
+            screen_dimensions,
+            input,
+            mut director,
+        ): Self::SystemData,
+    ) {
+        let map_width = (map_data.width * map_data.tilewidth) as f32;
+        let map_height = (map_data.height * map_data.tileheight) as f32;
+        if map_width <= 0.0 || map_height <= 0.0 || config.size <= 0.0 {
+            return;
+        }
+
+        // Minimap and world both have y axis going up
+        let to_minimap = |x: f32, y: f32| (
+            config.margin + ((x + map_width * 0.5) / map_width).max(0.0).min(1.0) * config.size,
+            config.margin + ((y + map_height * 0.5) / map_height).max(0.0).min(1.0) * config.size,
+        );
+
+        let enemies = (&units, &transforms).join()
+            .filter(|(unit, _)| unit.team != PLAYER_TEAM)
+            .map(|(_, t)| to_minimap(t.translation().x, t.translation().y))
+            .collect::<Vec<_>>();
+        let tower_positions = (&towers, &transforms).join()
+            .map(|(_, t)| to_minimap(t.translation().x, t.translation().y))
+            .collect::<Vec<_>>();
+
+        MinimapSystem::place_dots(
+            &mut self.unit_dots, &enemies, (4.0, config.enemy_colour),
+            &entities, &mut ui_transforms, &mut ui_images, &mut hiddens,
+        );
+        MinimapSystem::place_dots(
+            &mut self.tower_dots, &tower_positions, (6.0, config.tower_colour),
+            &entities, &mut ui_transforms, &mut ui_images, &mut hiddens,
+        );
+
+        let camera_entity = match active_camera.entity
+            .or_else(|| (&entities, &cameras).join().next().map(|(e, _)| e))
+        {
+            Some(e) => e,
+            None => return,
+        };
+
+        // Jump to the clicked point, dragging keeps moving the camera
+        if let Some((mouse_x, mouse_y)) = input.mouse_position() {
+            let ui_x = mouse_x - config.margin;
+            let ui_y = screen_dimensions.height() - mouse_y - config.margin;
+            let inside = ui_x >= 0.0 && ui_y >= 0.0 && ui_x <= config.size && ui_y <= config.size;
+
+            if inside && input.action_is_down("select").unwrap_or(false) {
+                if let Some(camera_transform) = transforms.get_mut(camera_entity) {
+                    let x = ui_x / config.size * map_width - map_width * 0.5;
+                    let y = ui_y / config.size * map_height - map_height * 0.5;
+                    let (x, y) = clamp_to_map(x, y, camera_transform.scale().x, &map_data, &screen_dimensions);
+
+                    director.cancel();
+                    camera_transform.set_translation_x(x);
+                    camera_transform.set_translation_y(y);
+                }
+            }
+        }
+
+        let camera_transform = match transforms.get(camera_entity) {
+            Some(e) => e,
+            None => return,
+        };
+        let scale = camera_transform.scale().x;
+        let half_width = screen_dimensions.width() * world_per_pixel(&screen_dimensions, scale) * 0.5;
+        let half_height = screen_dimensions.height() * world_per_pixel(&screen_dimensions, scale) * 0.5;
+        let (x, y) = (camera_transform.translation().x, camera_transform.translation().y);
+        let (left, bottom) = to_minimap(x - half_width, y - half_height);
+        let (right, top) = to_minimap(x + half_width, y + half_height);
+
+        if self.viewport.is_empty() {
+            for _ in 0..4 {
+                let entity = entities.create();
+                ui_transforms.insert(entity, UiTransform::new(
+                    "minimap-viewport".to_string(), Anchor::BottomLeft, Anchor::BottomLeft,
+                    0.0, 0.0, 103.0, 1.0, 1.0,
+                )).unwrap();
+                ui_images.insert(entity, UiImage::SolidColor(config.viewport_colour)).unwrap();
+                self.viewport.push(entity);
+            }
+        }
+
+        let line = 2.0;
+        let borders = [
+            (left, top - line, right - left, line),
+            (left, bottom, right - left, line),
+            (left, bottom, line, top - bottom),
+            (right - line, bottom, line, top - bottom),
+        ];
+        for (entity, (border_x, border_y, width, height)) in self.viewport.iter().zip(borders.iter()) {
+            if let Some(ui_transform) = ui_transforms.get_mut(*entity) {
+                ui_transform.local_x = *border_x;
+                ui_transform.local_y = *border_y;
+                ui_transform.width = *width;
+                ui_transform.height = *height;
+            }
+        }
+    }
+}
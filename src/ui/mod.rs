@@ -1 +1,6 @@
 pub mod menu;
+pub mod tower_menu;
+pub mod minimap;
+pub mod speed_menu;
+pub mod info_panel;
+pub mod health_bar;
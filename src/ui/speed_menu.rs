@@ -0,0 +1,87 @@
+use amethyst::{
+    ui::{UiText, Anchor, UiTransform, TtfFormat, Interactable, UiEvent, UiEventType, UiFinder, FontHandle},
+    prelude::{World, WorldExt, Builder},
+    assets::Loader,
+    ecs::{Read, System, SystemData, Write, WriteStorage},
+    shrev::{EventChannel, ReaderId},
+};
+use crate::game_speed::{GameSpeed, GAME_SPEEDS};
+
+const ACTIVE_COLOUR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+fn create_button(world: &mut World, font: FontHandle, id: String, text: String, x: f32) {
+    let ui_text = UiText::new(font, text, COLOUR, 20.0);
+    let ui_transform = UiTransform::new(
+        id,
+        Anchor::TopRight,
+        Anchor::Middle,
+        x, -70.0, 200.0, 40.0, 30.0,
+    );
+
+    world
+        .create_entity()
+        .with(ui_text)
+        .with(ui_transform)
+        .with(Interactable)
+        .build();
+}
+
+pub fn create_speed_menu(world: &mut World) {
+    let font = world.read_resource::<Loader>().load(
+        "font/square.ttf", TtfFormat, (), &world.read_resource(),
+    );
+
+    create_button(world, font.clone(), "speed-pause".to_string(), "||".to_string(), -200.0);
+    for (i, speed) in GAME_SPEEDS.iter().enumerate() {
+        create_button(
+            world, font.clone(), format!("speed-{}", speed), format!("{}x", speed), -150.0 + i as f32 * 45.0,
+        );
+    }
+}
+
+// Handles clicks on the speed buttons and highlights the current speed
+#[derive(Default)]
+pub struct SpeedMenuSystem {
+    reader_id: Option<ReaderId<UiEvent>>,
+}
+
+impl<'s> System<'s> for SpeedMenuSystem {
+    type SystemData = (
+        WriteStorage<'s, UiText>,
+        UiFinder<'s>,
+        Read<'s, EventChannel<UiEvent>>,
+        Write<'s, GameSpeed>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<UiEvent>>().register_reader());
+    }
+
+    fn run(&mut self, (mut ui_texts, ui_finder, ui_events, mut game_speed): Self::SystemData) {
+        for event in ui_events.read(self.reader_id.as_mut().unwrap()) {
+            if event.event_type != UiEventType::Click {
+                continue;
+            }
+            if ui_finder.find("speed-pause") == Some(event.target) {
+                game_speed.toggle_pause();
+            }
+            for speed in GAME_SPEEDS.iter() {
+                if ui_finder.find(&format!("speed-{}", speed)) == Some(event.target) {
+                    game_speed.set(*speed);
+                }
+            }
+        }
+
+        if let Some(ui_text) = ui_finder.find("speed-pause").and_then(|e| ui_texts.get_mut(e)) {
+            ui_text.color = if game_speed.paused { ACTIVE_COLOUR } else { COLOUR };
+        }
+        for speed in GAME_SPEEDS.iter() {
+            let active = !game_speed.paused && game_speed.multiplier == *speed;
+            if let Some(ui_text) = ui_finder.find(&format!("speed-{}", speed)).and_then(|e| ui_texts.get_mut(e)) {
+                ui_text.color = if active { ACTIVE_COLOUR } else { COLOUR };
+            }
+        }
+    }
+}
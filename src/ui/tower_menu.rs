@@ -0,0 +1,184 @@
+use amethyst::{
+    ui::{UiText, Anchor, UiTransform, TtfFormat, Interactable, UiEvent, UiEventType, UiFinder, FontHandle},
+    prelude::{World, WorldExt, Builder},
+    assets::Loader,
+    ecs::{Entities, Read, ReadStorage, System, SystemData, Write, WriteExpect, WriteStorage},
+    renderer::SpriteRender,
+    shrev::{EventChannel, ReaderId},
+    core::{transform::Transform, Time},
+    input::{InputHandler, StringBindings},
+};
+use crate::tower::{Tower, TowerTypes};
+use crate::selection::Selection;
+use crate::UserData;
+use crate::economy::{credit, debit, Reason, TransactionLog};
+use crate::particle::ParticleEvent;
+use crate::locale::Locale;
+
+const UPGRADE_BUTTONS: usize = 3;
+
+fn create_label(world: &mut World, font: FontHandle, id: String, y: f32) {
+    let ui_text = UiText::new(font, "".to_string(), [1.0, 1.0, 1.0, 1.0], 20.0);
+    let ui_transform = UiTransform::new(
+        id,
+        Anchor::BottomRight,
+        Anchor::Middle,
+        -170.0, y, 200.0, 300.0, 30.0,
+    );
+
+    world
+        .create_entity()
+        .with(ui_text)
+        .with(ui_transform)
+        .with(Interactable)
+        .build();
+}
+
+pub fn create_tower_menu(world: &mut World) {
+    let font = world.read_resource::<Loader>().load(
+        "font/square.ttf", TtfFormat, (), &world.read_resource(),
+    );
+
+    create_label(world, font.clone(), "tower-title".to_string(), 180.0);
+    for i in 0..UPGRADE_BUTTONS {
+        create_label(world, font.clone(), format!("tower-upgrade-{}", i), 140.0 - i as f32 * 30.0);
+    }
+    create_label(world, font, "tower-sell".to_string(), 40.0);
+}
+
+fn set_text(ui_finder: &UiFinder<'_>, ui_texts: &mut WriteStorage<'_, UiText>, id: &str, text: String) {
+    if let Some(ui_text) = ui_finder.find(id).and_then(|e| ui_texts.get_mut(e)) {
+        ui_text.text = text;
+    }
+}
+
+// Shows upgrades and sell price of the selected tower and handles clicks on them,
+// `sell_tower` and `upgrade_tower` actions do the same as the first buttons
+#[derive(Default)]
+pub struct TowerMenuSystem {
+    reader_id: Option<ReaderId<UiEvent>>,
+    sell_down: bool,
+    upgrade_down: bool,
+}
+
+impl<'s> System<'s> for TowerMenuSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Tower>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, UiText>,
+        UiFinder<'s>,
+        Read<'s, EventChannel<UiEvent>>,
+        Write<'s, Selection>,
+        WriteExpect<'s, UserData>,
+        Read<'s, TowerTypes>,
+        ReadStorage<'s, Transform>,
+        Write<'s, EventChannel<ParticleEvent>>,
+        Read<'s, InputHandler<StringBindings>>,
+        Write<'s, TransactionLog>,
+        Read<'s, Time>,
+        Read<'s, Locale>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<UiEvent>>().register_reader());
+    }
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut towers,
+            mut sprites,
+            mut ui_texts,
+            ui_finder,
+            ui_events,
+            mut selection,
+            mut user_data,
+            tower_types,
+            transforms,
+            mut particle_events,
+            input,
+            mut log,
+            time,
+            locale,
+        ): Self::SystemData,
+    ) {
+        // Only towers have a menu, other selections are left alone
+        let selected = selection.entity
+            .filter(|e| entities.is_alive(*e) && towers.contains(*e));
+
+        let sell_down = input.action_is_down("sell_tower").unwrap_or(false);
+        let upgrade_down = input.action_is_down("upgrade_tower").unwrap_or(false);
+        let mut sell = sell_down && !self.sell_down;
+        let mut upgrade = if upgrade_down && !self.upgrade_down { Some(0) } else { None };
+        self.sell_down = sell_down;
+        self.upgrade_down = upgrade_down;
+
+        for event in ui_events.read(self.reader_id.as_mut().unwrap()) {
+            if event.event_type != UiEventType::Click {
+                continue;
+            }
+            if ui_finder.find("tower-sell") == Some(event.target) {
+                sell = true;
+            }
+            for i in 0..UPGRADE_BUTTONS {
+                if ui_finder.find(&format!("tower-upgrade-{}", i)) == Some(event.target) {
+                    upgrade = Some(i);
+                }
+            }
+        }
+
+        if let Some(entity) = selected {
+            if sell {
+                let price = towers.get(entity).unwrap().sell_price(tower_types.sell_refund);
+                credit(&mut user_data, &mut log, &time, price, Reason::Sell);
+                if let Some(transform) = transforms.get(entity) {
+                    particle_events.single_write(ParticleEvent {
+                        emitter: "coins".to_string(),
+                        pos: *transform.translation(),
+                    });
+                }
+                entities.delete(entity).unwrap();
+                selection.entity = None;
+            } else if let Some(i) = upgrade {
+                let tower = towers.get_mut(entity).unwrap();
+                let cost = tower.upgrades.get(i).map(|e| e.cost);
+                if cost.map_or(false, |c| debit(&mut user_data, &mut log, &time, c, Reason::Upgrade)) {
+                    tower.upgrade(i);
+                    if let Some(sprite) = sprites.get_mut(entity) {
+                        sprite.sprite_number = tower.tower_type.sprite_id as usize;
+                    }
+                }
+            }
+        }
+
+        let tower = selection.entity.and_then(|e| towers.get(e));
+
+        set_text(&ui_finder, &mut ui_texts, "tower-title", match tower {
+            Some(t) => locale.tr_with("tower_menu.title", &[
+                ("name", locale.data_name("tower", &t.tower_type.name)),
+                ("damage", t.tower_type.damage.to_string()),
+                ("range", t.tower_type.range.to_string()),
+            ]),
+            None => "".to_string(),
+        });
+        for i in 0..UPGRADE_BUTTONS {
+            let upgrade = tower.and_then(|t| t.upgrades.get(i));
+            set_text(&ui_finder, &mut ui_texts, &format!("tower-upgrade-{}", i), match upgrade {
+                Some(u) => locale.tr_with("tower_menu.upgrade", &[
+                    ("name", locale.data_name("upgrade", &u.name)),
+                    ("cost", u.cost.to_string()),
+                ]),
+                None => "".to_string(),
+            });
+        }
+        set_text(&ui_finder, &mut ui_texts, "tower-sell", match tower {
+            Some(t) => locale.tr_with("tower_menu.sell", &[
+                ("price", t.sell_price(tower_types.sell_refund).floor().to_string()),
+            ]),
+            None => "".to_string(),
+        });
+    }
+}
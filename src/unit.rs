@@ -1,7 +1,12 @@
 use amethyst::{
     prelude::*,
-    core::transform::Transform,
-    ecs::prelude::{Component, DenseVecStorage},
+    core::{transform::Transform, Named, Time},
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Entity, Join, LazyUpdate, Read, ReadStorage, System, Write,
+        WriteExpect, WriteStorage,
+    },
+    shrev::EventChannel,
+    derive::SystemDesc,
     assets::Handle,
     renderer::{
         SpriteRender, SpriteSheet,
@@ -12,8 +17,37 @@ use amethyst::{
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::utils::{load_sprite_sheet, get_world_spawn_points};
+use crate::utils::{load_sprite_sheet, get_world_spawn_points, tile_points_to_world};
 use crate::map::{LevelInfo};
+use crate::effect::{EffectTypes, StatusEffects};
+use crate::damage::DamageType;
+use crate::ability::{Ability, BossPhase};
+use crate::animation::{spawn_one_shot, Animation, AnimationClip, AnimationState};
+use crate::particle::{Emitter, EmitterTypes, ParticleEvent};
+use crate::audio::SoundEvent;
+use crate::script::ScriptEvent;
+use crate::camera_director::CameraDirector;
+use crate::difficulty::Difficulty;
+use crate::economy::{credit, Reason, TransactionLog};
+use crate::tower::{TowerTypes, PLAYER_TEAM};
+use crate::UserData;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Locomotion {
+    // Walks along the level paths
+    Ground,
+    // Ignores the paths and flies over any tile
+    Flying,
+    // Walks along the level paths under the ground
+    Burrowing,
+}
+
+impl Default for Locomotion {
+    fn default() -> Self {
+        Locomotion::Ground
+    }
+}
 
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct UnitType {
@@ -22,6 +56,42 @@ pub struct UnitType {
     pub sprite_id: u32,
     pub attack: f32,
     pub max_health: f32,
+    pub speed: f32,
+    #[serde(default)]
+    pub locomotion: Locomotion,
+    // Flat reduction of physical damage
+    #[serde(default)]
+    pub armour: f32,
+    // Part of the damage ignored for each damage type, negative values are weaknesses
+    #[serde(default)]
+    pub resistances: HashMap<DamageType, f32>,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    // Boss phases, ordered by decreasing health
+    #[serde(default)]
+    pub phases: Vec<BossPhase>,
+    // Clips by state name: "idle", "walk", "attack", "death", optionally with a direction like "walk_left"
+    #[serde(default)]
+    pub animations: HashMap<String, AnimationClip>,
+    // Money paid for the kill
+    #[serde(default)]
+    pub bounty: f32,
+    // Price in the endless mode wave budget, types without a cost are not generated
+    #[serde(default)]
+    pub cost: f32,
+    // Stronger variant added by the endless mode, generated waves pick it in place of the base type
+    #[serde(default)]
+    pub elite: bool,
+    // Lives taken when the unit reaches the goal
+    #[serde(default = "default_lives_cost")]
+    pub lives_cost: u32,
+    // Particle emitter following the unit, like a trail
+    #[serde(default)]
+    pub trail: Option<String>,
+}
+
+fn default_lives_cost() -> u32 {
+    1
 }
 
 #[derive(Default, Serialize, Deserialize)]
@@ -29,6 +99,22 @@ pub struct UnitTyes {
     pub types: Vec<UnitType>,
 }
 
+// Sprite sheet listed in assets/images/sprite_sheets.json
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct SpriteSheetInfo {
+    // Used as `sprite_name` by units, towers, effects, particles and animation clips
+    pub name: String,
+    pub image: String,
+    // Grid or list RON of the sprites
+    pub sprites: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct SpriteSheets {
+    pub sheets: Vec<SpriteSheetInfo>,
+}
+
+// Sprite sheets by name, loaded from `SpriteSheets`
 #[derive(Default)]
 pub struct SpriteData {
     pub sprite_handles: HashMap<String, Handle<SpriteSheet>>,
@@ -39,16 +125,49 @@ pub struct Unit {
     pub team: u32,
     pub unit_type: UnitType,
     pub health: f32,
+    // World positions the unit walks through, the last one is the goal
+    pub path: Vec<Vector3<f32>>,
+    pub waypoint: usize,
+    // Last movement direction
+    pub heading: (f32, f32),
+    // Damage absorbed before health while `shield_time` is running
+    pub shield: f32,
+    pub shield_time: f32,
+    pub ability_timers: Vec<f32>,
+    // Index of the next boss phase
+    pub phase: usize,
+    // Set once the unit reached the goal, it is removed when `attack_time` runs out
+    pub attacking: bool,
+    pub attack_time: f32,
 }
 
 impl Component for Unit {
     type Storage = DenseVecStorage<Self>;
 }
 
-fn initialise_unit<'a>(world: &'a mut World, type_name: &String, pos: Vector3<f32>, team: u32) {
+impl Unit {
+    pub fn new(unit_type: UnitType, path: Vec<Vector3<f32>>, waypoint: usize, team: u32) -> Unit {
+        Unit {
+            team: team,
+            health: unit_type.max_health,
+            unit_type: unit_type,
+            path: path,
+            waypoint: waypoint,
+            ..Default::default()
+        }
+    }
+
+    pub fn take_damage(&mut self, damage: f32) {
+        let absorbed = damage.min(self.shield);
+        self.shield -= absorbed;
+        self.health -= damage - absorbed;
+    }
+}
+
+fn initialise_unit<'a>(world: &'a mut World, type_name: &String, pos: Vector3<f32>, path: Vec<Vector3<f32>>, team: u32) -> Entity {
     let units_types = world.fetch::<UnitTyes>().types.clone();
-    let unit_type = units_types.iter().find(|&x| &x.name == type_name).unwrap();
-    
+    let unit_type = world.fetch::<Difficulty>().apply_unit(units_types.iter().find(|&x| &x.name == type_name).unwrap());
+
     let sprite_sheet_handle = world.fetch::<SpriteData>().sprite_handles[&unit_type.sprite_name].clone();
 
     let sprite_render = SpriteRender {
@@ -56,38 +175,82 @@ fn initialise_unit<'a>(world: &'a mut World, type_name: &String, pos: Vector3<f3
         sprite_number: unit_type.sprite_id as usize,
     };
 
-    world
+    let entity = world
         .create_entity()
         .with(sprite_render.clone())
-        .with(Unit{
-            team: team,
-            unit_type: unit_type.clone(),
-            health: unit_type.max_health
-        })
+        .with(Unit::new(unit_type.clone(), path, 0, team))
+        .with(Named::new(unit_type.name.clone()))
+        .with(StatusEffects::default())
+        .with(Animation::new(unit_type.animations.clone(), unit_type.sprite_name.clone()))
         .with(Transform::new(
             Translation3::new(pos.x as f32, pos.y as f32, 0.0),
             UnitQuaternion::from_euler_angles(0.0, 0.0, 0.0),
             Vector3::new(1.0, 1.0, 1.0)
         ))
         .build();
+    if let Some(trail) = &unit_type.trail {
+        world.write_storage::<Emitter>().insert(entity, Emitter::new(trail.clone())).unwrap();
+    }
+    entity
 }
 
 pub fn load_unit_info(world: &mut World) {
     world.register::<Unit>();
 
+    let sheets = world.fetch::<SpriteSheets>().sheets.clone();
     let mut sprite_data = SpriteData::default();
-
-    sprite_data.sprite_handles.insert(
-        "hyptosis_sprites".to_string(),
-        load_sprite_sheet(world, "images/hyptosis_sprites.png", "images/hyptosis_sprites.ron")
-    );
+    for sheet in sheets {
+        let handle = load_sprite_sheet(world, &sheet.image, &sheet.sprites);
+        if sprite_data.sprite_handles.insert(sheet.name.clone(), handle).is_some() {
+            println!("Sprite sheet {} is listed twice, the last one is used", sheet.name);
+        }
+    }
     world.insert::<SpriteData>(sprite_data);
 }
 
+// Every `sprite_name` of the loaded data must be a registered sprite sheet,
+// so spawning never looks up a missing one
+pub fn check_sprite_names(world: &mut World) {
+    let mut used = Vec::<(String, String)>::new();
+    for unit_type in world.fetch::<UnitTyes>().types.iter() {
+        used.push((format!("unit type {}", unit_type.name), unit_type.sprite_name.clone()));
+        for (state, clip) in unit_type.animations.iter() {
+            if let Some(sprite_name) = &clip.sprite_name {
+                used.push((format!("{} animation of unit type {}", state, unit_type.name), sprite_name.clone()));
+            }
+        }
+    }
+    for tower_type in world.fetch::<TowerTypes>().types.iter() {
+        used.push((format!("tower type {}", tower_type.name), tower_type.sprite_name.clone()));
+        if let Some(sprite_name) = tower_type.hit_effect.as_ref().and_then(|x| x.sprite_name.clone()) {
+            used.push((format!("hit effect of tower type {}", tower_type.name), sprite_name));
+        }
+    }
+    for effect_type in world.fetch::<EffectTypes>().types.iter() {
+        used.push((format!("effect type {}", effect_type.name), effect_type.sprite_name.clone()));
+    }
+    for emitter_type in world.fetch::<EmitterTypes>().types.iter() {
+        used.push((format!("particle emitter {}", emitter_type.name), emitter_type.sprite_name.clone()));
+    }
+
+    let sprite_data = world.fetch::<SpriteData>();
+    let mut known = sprite_data.sprite_handles.keys().cloned().collect::<Vec<_>>();
+    known.sort();
+    let missing = used.iter().filter(|(_, x)| !sprite_data.sprite_handles.contains_key(x)).collect::<Vec<_>>();
+    for (user, sprite_name) in missing.iter() {
+        println!(
+            "Sprite sheet {} of {} is not registered in images/sprite_sheets.json, known sheets: {}",
+            sprite_name, user, known.join(", "),
+        );
+    }
+    if !missing.is_empty() {
+        std::process::exit(1);
+    }
+}
+
 pub fn spawn_unit(world: &mut World, wave: u32, index: u32) {
-    let spawn_points = get_world_spawn_points(world);
     let spawn_points_len = world.fetch::<LevelInfo>().enemy_spawn.len();
-    let pos = spawn_points[(index % spawn_points_len as u32) as usize];
+    let spawn_index = (index % spawn_points_len as u32) as usize;
 
     let unit_name = match world.fetch::<LevelInfo>().get_unit_by_index(wave, index) {
         Some(e) => e,
@@ -96,6 +259,209 @@ pub fn spawn_unit(world: &mut World, wave: u32, index: u32) {
             std::process::exit(1);
         }
     };
-    initialise_unit(world, &unit_name, pos, 1);
+    let pos = spawn_unit_type(world, &unit_name, spawn_index);
     println!("{}) Spwan {} in {}, {}", &index + 1, &unit_name, &pos.x, &pos.y);
 }
+
+// Spawns an enemy of the type at a spawn point of the level, returns where
+pub fn spawn_unit_type(world: &mut World, unit_name: &String, spawn_index: usize) -> Vector3<f32> {
+    let spawn_points = get_world_spawn_points(world);
+    let pos = spawn_points[spawn_index];
+
+    let path = get_unit_path(world, unit_name, spawn_index);
+    let entity = initialise_unit(world, unit_name, pos, path, 1);
+
+    // Show bosses to the player
+    let is_boss = world.fetch::<UnitTyes>().types.iter().any(|x| &x.name == unit_name && !x.phases.is_empty());
+    if is_boss {
+        world.write_resource::<CameraDirector>().move_to_entity(entity, Some(1.5), 1.0, 1.5);
+    }
+    pos
+}
+
+fn get_unit_path(world: &World, type_name: &String, spawn_index: usize) -> Vec<Vector3<f32>> {
+    let locomotion = world.fetch::<UnitTyes>().types.iter()
+        .find(|&x| &x.name == type_name).unwrap().locomotion;
+
+    let mut points = match locomotion {
+        Locomotion::Flying => world.fetch::<LevelInfo>().flying_path.clone(),
+        Locomotion::Ground | Locomotion::Burrowing => world.fetch::<LevelInfo>().enemy_paths[spawn_index].clone(),
+    };
+    points.push(world.fetch::<LevelInfo>().enemy_goal.clone());
+    tile_points_to_world(world, &points)
+}
+
+#[derive(SystemDesc)]
+pub struct UnitMoveSystem;
+
+impl<'s> System<'s> for UnitMoveSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Unit>,
+        WriteStorage<'s, Transform>,
+        ReadStorage<'s, StatusEffects>,
+        Write<'s, CameraDirector>,
+        Read<'s, Time>,
+        WriteExpect<'s, UserData>,
+        Write<'s, EventChannel<SoundEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut units,
+            mut transforms,
+            status_effects,
+            mut director,
+            time,
+            mut user_data,
+            mut sound_events,
+        ): Self::SystemData,
+    ) {
+        for (entity, unit, transform, effects) in (&entities, &mut units, &mut transforms, status_effects.maybe()).join() {
+            let target = match unit.path.get(unit.waypoint) {
+                Some(e) => *e,
+                None => {
+                    // Unit reached the goal, it attacks for one play of its attack clip
+                    if !unit.attacking {
+                        unit.attacking = true;
+                        unit.attack_time = unit.unit_type.animations.get(AnimationState::Attack.name()).map_or(0.0, |x| x.duration());
+                        if unit.team != PLAYER_TEAM {
+                            user_data.lives = user_data.lives.saturating_sub(unit.unit_type.lives_cost);
+                            sound_events.single_write(SoundEvent::Leak);
+                        }
+                        if !director.is_busy() && director.following().is_none() {
+                            let pos = transform.translation();
+                            director.move_to(pos.x, pos.y, None, 0.6, 1.0);
+                        }
+                    }
+                    unit.attack_time -= time.delta_seconds();
+                    if unit.attack_time <= 0.0 {
+                        entities.delete(entity).unwrap();
+                    }
+                    continue;
+                }
+            };
+
+            let translation = transform.translation();
+            let direction = Vector3::new(target.x - translation.x, target.y - translation.y, 0.0);
+            let distance = direction.norm();
+            if distance > 0.0 {
+                unit.heading = (direction.x, direction.y);
+            }
+            let speed_multiplier = effects.map_or(1.0, |x| x.speed_multiplier());
+            let step = unit.unit_type.speed * speed_multiplier * time.delta_seconds();
+
+            if distance <= step {
+                transform.set_translation_x(target.x);
+                transform.set_translation_y(target.y);
+                unit.waypoint += 1;
+            } else {
+                let offset = direction / distance * step;
+                transform.prepend_translation_x(offset.x);
+                transform.prepend_translation_y(offset.y);
+            }
+        }
+    }
+}
+
+// Removes killed units and triggers their on death abilities
+#[derive(Default)]
+pub struct UnitDeathSystem;
+
+impl<'s> System<'s> for UnitDeathSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Unit>,
+        ReadStorage<'s, Transform>,
+        Read<'s, UnitTyes>,
+        Read<'s, SpriteData>,
+        Read<'s, LazyUpdate>,
+        Write<'s, EventChannel<ParticleEvent>>,
+        Write<'s, EventChannel<SoundEvent>>,
+        Write<'s, EventChannel<ScriptEvent>>,
+        WriteExpect<'s, UserData>,
+        Write<'s, TransactionLog>,
+        Read<'s, Time>,
+        Read<'s, Difficulty>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            units,
+            transforms,
+            unit_types,
+            sprite_data,
+            lazy_update,
+            mut particle_events,
+            mut sound_events,
+            mut script_events,
+            mut user_data,
+            mut log,
+            time,
+            difficulty,
+        ): Self::SystemData,
+    ) {
+        for (entity, unit, transform) in (&entities, &units, &transforms).join() {
+            if unit.health > 0.0 {
+                continue;
+            }
+            // Units attacking the goal already took lives
+            if unit.team != PLAYER_TEAM && !unit.attacking {
+                credit(&mut user_data, &mut log, &time, unit.unit_type.bounty, Reason::Bounty);
+                if unit.unit_type.bounty > 0.0 {
+                    particle_events.single_write(ParticleEvent {
+                        emitter: "coins".to_string(),
+                        pos: *transform.translation(),
+                    });
+                }
+            }
+
+            for ability in &unit.unit_type.abilities {
+                if let Ability::SpawnOnDeath { unit: child_name, count } = ability {
+                    let child_type = match unit_types.types.iter().find(|&x| &x.name == child_name) {
+                        Some(e) => difficulty.apply_unit(e),
+                        None => {
+                            println!("Unknown unit type {} in {} abilities", child_name, unit.unit_type.name);
+                            continue;
+                        }
+                    };
+                    for i in 0..*count {
+                        let mut child_transform = transform.clone();
+                        child_transform.prepend_translation_x((i as f32 - *count as f32 * 0.5) * 8.0);
+
+                        let child = lazy_update
+                            .create_entity(&entities)
+                            .with(SpriteRender {
+                                sprite_sheet: sprite_data.sprite_handles[&child_type.sprite_name].clone(),
+                                sprite_number: child_type.sprite_id as usize,
+                            })
+                            .with(Unit::new(child_type.clone(), unit.path.clone(), unit.waypoint, unit.team))
+                            .with(Named::new(child_type.name.clone()))
+                            .with(StatusEffects::default())
+                            .with(Animation::new(child_type.animations.clone(), child_type.sprite_name.clone()))
+                            .with(child_transform)
+                            .build();
+                        if let Some(trail) = &child_type.trail {
+                            lazy_update.insert(child, Emitter::new(trail.clone()));
+                        }
+                    }
+                }
+            }
+
+            particle_events.single_write(ParticleEvent {
+                emitter: "death".to_string(),
+                pos: *transform.translation(),
+            });
+            sound_events.single_write(SoundEvent::Death);
+            script_events.single_write(ScriptEvent::UnitDeath(unit.unit_type.name.clone(), unit.team));
+            if let Some(clip) = unit.unit_type.animations.get(AnimationState::Death.name()) {
+                spawn_one_shot(&lazy_update, &entities, &sprite_data, clip, &unit.unit_type.sprite_name, transform.clone());
+            }
+            entities.delete(entity).unwrap();
+        }
+    }
+}
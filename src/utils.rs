@@ -1,7 +1,7 @@
 use amethyst::{
     prelude::*,
     renderer::{
-        ImageFormat, SpriteSheet, SpriteSheetFormat, Texture,
+        ImageFormat, SpriteRender, SpriteSheet, SpriteSheetFormat, Texture,
     },
     ecs::Join,
     assets::{AssetStorage, Loader, Handle},
@@ -39,6 +39,13 @@ pub fn load_sprite_sheet(world: &mut World, image_path: &str, ron_path: &str) ->
     )
 }
 
+// Width and height of a sprite, None while its sheet is loading
+pub fn sprite_size(sprite_sheets: &AssetStorage<SpriteSheet>, sprite: &SpriteRender) -> Option<(f32, f32)> {
+    let sprite_sheet = sprite_sheets.get(&sprite.sprite_sheet)?;
+    let sprite = sprite_sheet.sprites.get(sprite.sprite_number)?;
+    Some((sprite.width, sprite.height))
+}
+
 pub fn load_json_data<T: DeserializeOwned>(json_path: &str) -> T {
     let json_string = match fs::read_to_string(&json_path) {
         Ok(e) => e,
@@ -57,19 +64,22 @@ pub fn load_json_data<T: DeserializeOwned>(json_path: &str) -> T {
 }
 
 pub fn get_world_spawn_points(world: &mut World) -> Vec::<Vector3<f32>> {
-    let mut spawn_points = Vec::<Vector3<f32>>::new();
-    let spawn_points_len = world.fetch::<LevelInfo>().enemy_spawn.len();
+    let enemy_spawn = world.fetch::<LevelInfo>().enemy_spawn.clone();
+    tile_points_to_world(world, &enemy_spawn)
+}
+
+pub fn tile_points_to_world(world: &World, points: &Vec<Vec<u32>>) -> Vec::<Vector3<f32>> {
+    let mut world_points = Vec::<Vector3<f32>>::new();
 
-    let storage_tilemap = world.write_storage::<TileMap::<BlockTile, MortonEncoder2D>>();
+    let storage_tilemap = world.read_storage::<TileMap::<BlockTile, MortonEncoder2D>>();
     {
-        let tilemap_vec = (storage_tilemap).join().collect::<Vec<_>>();
+        let tilemap_vec = (&storage_tilemap).join().collect::<Vec<_>>();
         {
             let tile_map = tilemap_vec.first().unwrap();
-            for i_point in 0..spawn_points_len {
-                let point = &world.fetch::<LevelInfo>().enemy_spawn[i_point];
-                spawn_points.push(tile_map.to_world(&Point3::new(point[0] as u32, point[1] as u32, 2), None));
+            for point in points {
+                world_points.push(tile_map.to_world(&Point3::new(point[0] as u32, point[1] as u32, 2), None));
             }
         }
     }
-    spawn_points
+    world_points
 }